@@ -1,10 +1,10 @@
 use crate::Square;
-use std::ops::{BitAnd, BitOr, BitXor};
+use std::ops::{BitAnd, BitOr, BitXor, Not};
 
 const BIT_BOARD_FULL: u128 =
     0b_111111111_111111111_111111111_111111111_111111111_111111111_111111111_111111111_111111111;
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 pub struct Bitboard(pub u128);
 
 impl BitAnd for Bitboard {
@@ -31,6 +31,14 @@ impl BitXor for Bitboard {
     }
 }
 
+impl Not for Bitboard {
+    type Output = Bitboard;
+
+    fn not(self) -> Self::Output {
+        Self(!self.0 & BIT_BOARD_FULL)
+    }
+}
+
 impl Bitboard {
     pub const fn empty() -> Self {
         Self(0)
@@ -39,17 +47,20 @@ impl Bitboard {
         Self(BIT_BOARD_FULL)
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
     pub fn is_filled(&self, sq: &Square) -> bool {
-        let pos = sq_to_pos(sq);
-        self.0 & (1 << pos) != 0
+        self.0 & (1 << sq.index()) != 0
     }
     pub fn fill(&mut self, sq: &Square) {
-        let pos = sq_to_pos(sq);
+        let pos = sq.index();
         assert_eq!(self.0 & (1 << pos), 0);
         self.0 ^= 1 << pos;
     }
     pub fn remove(&mut self, sq: &Square) {
-        let pos = sq_to_pos(sq);
+        let pos = sq.index();
         assert_ne!(self.0 & (1 << pos), 0);
         self.0 ^= 1 << pos;
     }
@@ -59,10 +70,29 @@ impl Bitboard {
         Bitboard(reversed >> 47)
     }
 
+    /// Flips files 1 and 9, keeping the ranks.
+    pub fn mirror(&self) -> Self {
+        let mut mirrored = 0;
+        for file in 1..=9 {
+            let column = self.0 & super::bit_file(file);
+            // File `file` is at column `9 - file` and moves to column `file - 1`.
+            mirrored |= if file < 5 {
+                column >> (10 - 2 * file)
+            } else {
+                column << (2 * file - 10)
+            };
+        }
+        Bitboard(mirrored)
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = u32> {
         BitIterator(self.0)
     }
 
+    pub fn squares(&self) -> impl Iterator<Item = Square> {
+        self.iter().filter_map(|pos| Square::from_index(pos as u8))
+    }
+
     pub fn file_count_ones(&self, file: u8) -> u32 {
         let file_only = super::bit_file(file) & self.0;
         file_only.count_ones()
@@ -85,8 +115,10 @@ impl Iterator for BitIterator {
     }
 }
 
-fn sq_to_pos(sq: &Square) -> u8 {
-    (sq.rank - 1) * 9 + 9 - sq.file
+impl From<Square> for Bitboard {
+    fn from(sq: Square) -> Self {
+        Bitboard(1 << sq.index())
+    }
 }
 
 #[cfg(test)]
@@ -103,6 +135,21 @@ mod tests {
         assert_eq!(input, Bitboard(input).rotate180().rotate180().0);
     }
 
+    #[test]
+    fn test_mirror() {
+        let mut bb = Bitboard::empty();
+        bb.fill(&Square::at(1, 1));
+        bb.fill(&Square::at(3, 5));
+        bb.fill(&Square::at(5, 9));
+        let mirrored = bb.mirror();
+        assert_eq!(
+            mirrored.squares().collect::<Vec<_>>(),
+            vec![Square::at(9, 1), Square::at(7, 5), Square::at(5, 9),]
+        );
+        assert_eq!(bb.0, mirrored.mirror().0);
+        assert_eq!(Bitboard::full().mirror().0, Bitboard::full().0);
+    }
+
     #[test]
     fn test_bit_iterator() {
         let v = Bitboard(0b1000100101).iter().collect::<Vec<_>>();
@@ -0,0 +1,134 @@
+use crate::piece::attacks::{rays, DIRECTIONS};
+use crate::{Bitboard, Square};
+
+/// Directions on the board, seen from Black.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
+pub enum Direction {
+    Up,
+    Down,
+    Right,
+    Left,
+    UpRight,
+    UpLeft,
+    DownRight,
+    DownLeft,
+}
+
+const ALL_DIRECTIONS: [Direction; 8] = [
+    Direction::Up,
+    Direction::Down,
+    Direction::Right,
+    Direction::Left,
+    Direction::UpRight,
+    Direction::UpLeft,
+    Direction::DownRight,
+    Direction::DownLeft,
+];
+
+const fn opposite(dir: usize) -> usize {
+    let (d_rank, d_file) = DIRECTIONS[dir];
+    let mut opposite = 0;
+    while DIRECTIONS[opposite].0 != -d_rank || DIRECTIONS[opposite].1 != -d_file {
+        opposite += 1;
+    }
+    opposite
+}
+
+/// `[between, line, direction + 1]` for every pair of squares, with zeros for unaligned pairs.
+const fn geometry() -> [[[u128; 81]; 81]; 3] {
+    let rays = rays();
+    let mut tables = [[[0; 81]; 81]; 3];
+    let mut a = 0;
+    while a < 81 {
+        let mut dir = 0;
+        while dir < 8 {
+            let back = opposite(dir);
+            let line = rays[dir][a] | rays[back][a] | (1 << a);
+            let mut b = 0;
+            while b < 81 {
+                if rays[dir][a] & (1 << b) != 0 {
+                    tables[0][a][b] = rays[dir][a] & rays[back][b];
+                    tables[1][a][b] = line;
+                    tables[2][a][b] = dir as u128 + 1;
+                }
+                b += 1;
+            }
+            dir += 1;
+        }
+        a += 1;
+    }
+    tables
+}
+
+static GEOMETRY: [[[u128; 81]; 81]; 3] = geometry();
+
+impl Direction {
+    /// The direction from `from` towards `to`, if they share a file, rank or diagonal.
+    pub fn between(from: &Square, to: &Square) -> Option<Direction> {
+        let dir = GEOMETRY[2][from.index()][to.index()];
+        match dir {
+            0 => None,
+            dir => Some(ALL_DIRECTIONS[dir as usize - 1]),
+        }
+    }
+}
+
+impl Bitboard {
+    /// Squares strictly between two aligned squares, or nothing if they are not aligned.
+    pub fn between(a: &Square, b: &Square) -> Bitboard {
+        Bitboard(GEOMETRY[0][a.index()][b.index()])
+    }
+
+    /// The whole line through two aligned squares, or nothing if they are not aligned.
+    pub fn line(a: &Square, b: &Square) -> Bitboard {
+        Bitboard(GEOMETRY[1][a.index()][b.index()])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_geometry() {
+        let sq = Square::at;
+
+        let between = Bitboard::between(&sq(5, 9), &sq(5, 5));
+        assert_eq!(
+            between.squares().collect::<Vec<_>>(),
+            vec![sq(5, 6), sq(5, 7), sq(5, 8)]
+        );
+        assert_eq!(between, Bitboard::between(&sq(5, 5), &sq(5, 9)));
+        assert!(Bitboard::between(&sq(5, 9), &sq(5, 8)).is_empty());
+        assert!(Bitboard::between(&sq(5, 9), &sq(4, 7)).is_empty());
+        assert_eq!(Bitboard::between(&sq(9, 9), &sq(1, 1)).0.count_ones(), 7);
+
+        assert_eq!(Bitboard::line(&sq(1, 1), &sq(3, 3)).0.count_ones(), 9);
+        assert!(Bitboard::line(&sq(1, 1), &sq(3, 3)).is_filled(&sq(9, 9)));
+        assert_eq!(Bitboard::line(&sq(5, 1), &sq(4, 1)).0.count_ones(), 9);
+        assert!(Bitboard::line(&sq(5, 9), &sq(4, 7)).is_empty());
+
+        assert_eq!(
+            Direction::between(&sq(5, 9), &sq(5, 1)),
+            Some(Direction::Up)
+        );
+        assert_eq!(
+            Direction::between(&sq(5, 1), &sq(5, 9)),
+            Some(Direction::Down)
+        );
+        assert_eq!(
+            Direction::between(&sq(5, 5), &sq(1, 5)),
+            Some(Direction::Right)
+        );
+        assert_eq!(
+            Direction::between(&sq(5, 5), &sq(9, 1)),
+            Some(Direction::UpLeft)
+        );
+        assert_eq!(
+            Direction::between(&sq(5, 5), &sq(3, 7)),
+            Some(Direction::DownRight)
+        );
+        assert_eq!(Direction::between(&sq(5, 5), &sq(4, 7)), None);
+        assert_eq!(Direction::between(&sq(5, 5), &sq(5, 5)), None);
+    }
+}
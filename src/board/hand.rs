@@ -0,0 +1,40 @@
+use super::PIECE_TYPES;
+use crate::Piece;
+
+/// A read-only view of the pieces one side holds in hand.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
+pub struct Hand<'a> {
+    counts: &'a [u8; PIECE_TYPES],
+}
+
+impl<'a> Hand<'a> {
+    pub(crate) fn new(counts: &'a [u8; PIECE_TYPES]) -> Self {
+        Self { counts }
+    }
+
+    pub fn count(&self, piece: Piece) -> u8 {
+        self.counts[piece.to_usize()]
+    }
+
+    pub fn contains(&self, piece: Piece) -> bool {
+        self.count(piece) > 0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.iter().all(|&count| count == 0)
+    }
+
+    /// The number of pieces in hand.
+    pub fn len(&self) -> usize {
+        self.counts.iter().map(|&count| count as usize).sum()
+    }
+
+    /// Iterates the kinds of pieces in hand with their counts, from pawns to rooks.
+    pub fn iter(&self) -> impl Iterator<Item = (Piece, u8)> + 'a {
+        let counts = self.counts;
+        Piece::HAND
+            .iter()
+            .map(move |&piece| (piece, counts[piece.to_usize()]))
+            .filter(|&(_, count)| count > 0)
+    }
+}
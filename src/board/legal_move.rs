@@ -1,68 +1,660 @@
+use super::{bit_file, bit_rank, MoveList, PIECE_TYPES};
+use crate::error::ShogiUtilError::InvalidMove;
 use crate::model::LegalMove;
-use crate::{Bitboard, Board, Color, Move, Piece, Square};
-
-pub(crate) fn generate_legal_moves(board: &Board) -> Vec<LegalMove> {
-    let mut moves = vec![];
-    for (piece_id, &piece_bb) in board.piece_bb.iter().enumerate() {
-        let piece = Piece::from(piece_id as u8);
-        let bb = piece_bb & board.occupied[0];
-        for pos in bb.iter() {
-            let pos_i = pos / 9;
-            let pos_j = pos % 9;
-            let from = Square::from_pos(pos_i as usize, pos_j as usize);
-            let mut destinations = vec![];
-            piece.generate_moves(&from, &board.occupied, &mut destinations);
-            for to in destinations {
-                if from.rank <= 3 || to.rank <= 3 {
-                    if let Some(piece) = piece.promote() {
-                        moves.push(LegalMove {
-                            mv: Move {
-                                color: Color::Black,
-                                from: Some(from.clone()),
-                                to: to.clone(),
-                                piece,
-                            },
-                            promoted: true,
-                        });
-                    }
-                }
+use crate::{Bitboard, Board, Color, Move, Piece, Result, Square};
+
+pub(crate) fn generate_legal_moves(board: &Board, color: Color, moves: &mut MoveList) {
+    let own = board.occupied[color.to_usize()];
+    let empty = !(board.occupied[0] | board.occupied[1]);
+    moves.clear();
+    generate_travels(board, color, own, !own, moves);
+    generate_drops(board, color, empty, moves);
+    retain_legal(board, moves);
+}
+
+/// Legal moves which capture an opponent piece, with and without promotion.
+pub(crate) fn generate_captures(board: &Board, color: Color, moves: &mut MoveList) {
+    let own = board.occupied[color.to_usize()];
+    let opponent = board.occupied[color.opponent().to_usize()];
+    moves.clear();
+    generate_travels(board, color, own, opponent, moves);
+    retain_legal(board, moves);
+}
+
+/// Legal moves which capture nothing, including drops.
+pub(crate) fn generate_quiets(board: &Board, color: Color, moves: &mut MoveList) {
+    let own = board.occupied[color.to_usize()];
+    let empty = !(board.occupied[0] | board.occupied[1]);
+    moves.clear();
+    generate_travels(board, color, own, empty, moves);
+    generate_drops(board, color, empty, moves);
+    retain_legal(board, moves);
+}
+
+/// Legal moves which give check, including discovered checks.
+pub(crate) fn generate_checks(board: &Board, color: Color, moves: &mut MoveList) {
+    moves.clear();
+    let king = match board.king_square(color.opponent()) {
+        Some(king) => king,
+        None => return,
+    };
+    let own = board.occupied[color.to_usize()];
+    let occupied = board.occupied[0] | board.occupied[1];
+
+    // The squares from which each piece of `color` would attack the king.
+    let mut check_squares = [Bitboard::empty(); PIECE_TYPES];
+    for (piece_id, piece) in Piece::ALL.iter().enumerate().skip(1) {
+        check_squares[piece_id] = piece.attacks(color.opponent(), &king, occupied);
+    }
+    // Own pieces which uncover an attack on the king when they leave its line.
+    let discoverers = board.blockers_for_king(color.opponent()) & own;
+    let gives_check = |from: Option<Square>, piece: Piece, to: &Square| {
+        check_squares[piece.to_usize()].is_filled(to)
+            || from.is_some_and(|from| {
+                discoverers.is_filled(&from) && !Bitboard::line(&king, &from).is_filled(to)
+            })
+    };
+
+    for (piece_id, &piece_bb) in board.piece_bb.iter().enumerate().skip(1) {
+        let piece = Piece::ALL[piece_id];
+        let mut targets = check_squares[piece_id];
+        if let Some(promoted) = piece.promote() {
+            targets = targets | check_squares[promoted.to_usize()];
+        }
+        for from in (piece_bb & own).squares() {
+            let targets = if discoverers.is_filled(&from) {
+                !own & !opponent_king(board, color)
+            } else {
+                targets & !own
+            };
+            push_travels(
+                color,
+                piece,
+                &from,
+                piece.attacks(color, &from, occupied) & targets,
+                moves,
+            );
+        }
+    }
+    for (piece_id, &count) in board.pieces_in_hand[color.to_usize()].iter().enumerate() {
+        if count > 0 {
+            let piece = Piece::ALL[piece_id];
+            let targets = check_squares[piece_id] & !occupied;
+            push_drops(
+                color,
+                piece,
+                drop_targets(board, color, piece, targets),
+                moves,
+            );
+        }
+    }
+    moves.retain(|mv| gives_check(mv.mv.from, mv.mv.piece, &mv.mv.to));
+    retain_legal(board, moves);
+}
+
+/// Legal moves which get the king of `color` out of check, or nothing if it is not in check.
+pub(crate) fn generate_evasions(board: &Board, color: Color, moves: &mut MoveList) {
+    moves.clear();
+    let king = match board.king_square(color) {
+        Some(king) => king,
+        None => return,
+    };
+    let checkers = board.checkers(color);
+    if checkers.is_empty() {
+        return;
+    }
+
+    let own = board.occupied[color.to_usize()];
+    let king_bb = board.piece_bb[Piece::King.to_usize()] & own;
+    generate_travels(board, color, king_bb, !own, moves);
+    let mut checker_squares = checkers.squares();
+    if let (Some(checker), None) = (checker_squares.next(), checker_squares.next()) {
+        // A single check can also be answered by capturing the checker or interposing.
+        let between = Bitboard::between(&king, &checker);
+        generate_travels(board, color, own & !king_bb, between | checkers, moves);
+        generate_drops(board, color, between, moves);
+    }
+    retain_legal(board, moves);
+}
+
+fn retain_legal(board: &Board, moves: &mut MoveList) {
+    let color = match moves.first() {
+        Some(mv) => mv.mv.color,
+        None => return,
+    };
+    let pinned = board.pinned_pieces(color);
+    let in_check = board.in_check(color);
+    moves.retain(|mv| {
+        !leaves_king_in_check(board, &mv.mv, pinned, in_check) && !is_uchifuzume(board, &mv.mv)
+    });
+}
+
+/// Pushes the moves of the pieces in `pieces` to `targets`, ignoring checks.
+fn generate_travels(
+    board: &Board,
+    color: Color,
+    pieces: Bitboard,
+    targets: Bitboard,
+    moves: &mut MoveList,
+) {
+    let targets = targets & !opponent_king(board, color);
+    let occupied = board.occupied[0] | board.occupied[1];
+    for (piece_id, &piece_bb) in board.piece_bb.iter().enumerate().skip(1) {
+        let piece = Piece::ALL[piece_id];
+        for from in (piece_bb & pieces).squares() {
+            let destinations = piece.attacks(color, &from, occupied) & targets;
+            push_travels(color, piece, &from, destinations, moves);
+        }
+    }
+}
+
+/// The square of the opponent king, which `apply_move` refuses to capture.
+fn opponent_king(board: &Board, color: Color) -> Bitboard {
+    board.piece_bb[Piece::King.to_usize()] & board.occupied[color.opponent().to_usize()]
+}
+
+/// Pushes the moves of `piece` from `from` to `destinations`, with and without promotion.
+fn push_travels(
+    color: Color,
+    piece: Piece,
+    from: &Square,
+    destinations: Bitboard,
+    moves: &mut MoveList,
+) {
+    for to in destinations.squares() {
+        if can_promote(color, from, &to) {
+            if let Some(piece) = piece.promote() {
                 moves.push(LegalMove {
                     mv: Move {
-                        color: Color::Black,
-                        from: Some(from.clone()),
+                        color,
+                        from: Some(*from),
                         to,
                         piece,
                     },
-                    promoted: false,
+                    promoted: true,
                 });
             }
         }
-    }
-
-    let occupied = board.occupied[0] | board.occupied[1];
-    let unoccupied = Bitboard::full() ^ occupied;
-    for (piece_id, &count) in board.pieces_in_hand[0].iter().enumerate() {
-        if count == 0 {
-            continue;
-        }
-        let piece = Piece::from(piece_id as u8);
-        for pos in unoccupied.iter() {
-            let pos_i = pos / 9;
-            let pos_j = pos % 9;
-            let to = Square::from_pos(pos_i as usize, pos_j as usize);
-            if piece == Piece::Pawn && board.piece_bb[piece_id].file_count_ones(to.file) > 0 {
-                continue;
-            }
+        if !is_dead_square(color, piece, &to) {
             moves.push(LegalMove {
                 mv: Move {
-                    color: Color::Black,
-                    from: None,
+                    color,
+                    from: Some(*from),
                     to,
                     piece,
                 },
                 promoted: false,
-            })
+            });
+        }
+    }
+}
+
+/// Pushes the drops to the empty squares in `targets`, ignoring checks.
+fn generate_drops(board: &Board, color: Color, targets: Bitboard, moves: &mut MoveList) {
+    for (piece_id, &count) in board.pieces_in_hand[color.to_usize()].iter().enumerate() {
+        if count == 0 {
+            continue;
+        }
+        let piece = Piece::ALL[piece_id];
+        push_drops(
+            color,
+            piece,
+            drop_targets(board, color, piece, targets),
+            moves,
+        );
+    }
+}
+
+fn push_drops(color: Color, piece: Piece, targets: Bitboard, moves: &mut MoveList) {
+    for to in targets.squares() {
+        moves.push(LegalMove {
+            mv: Move {
+                color,
+                from: None,
+                to,
+                piece,
+            },
+            promoted: false,
+        })
+    }
+}
+
+/// Returns the squares in `empty` where `piece` of `color` may be dropped, ignoring checks.
+fn drop_targets(board: &Board, color: Color, piece: Piece, empty: Bitboard) -> Bitboard {
+    let (first, second) = match color {
+        Color::Black => (bit_rank(1), bit_rank(2)),
+        Color::White => (bit_rank(9), bit_rank(8)),
+    };
+    match piece {
+        Piece::Pawn => {
+            let pawns = board.piece_bb[Piece::Pawn.to_usize()] & board.occupied[color.to_usize()];
+            let files = pawns
+                .squares()
+                .fold(0, |files, sq| files | bit_file(sq.file()));
+            empty & !Bitboard(first | files)
+        }
+        Piece::Lance => empty & !Bitboard(first),
+        Piece::Knight => empty & !Bitboard(first | second),
+        _ => empty,
+    }
+}
+
+fn in_promotion_zone(color: Color, sq: &Square) -> bool {
+    sq.relative_rank(color) <= 3
+}
+
+pub(crate) fn can_promote(color: Color, from: &Square, to: &Square) -> bool {
+    in_promotion_zone(color, from) || in_promotion_zone(color, to)
+}
+
+/// Returns true if `piece` of `color` standing on `sq` could never move again.
+pub(crate) fn is_dead_square(color: Color, piece: Piece, sq: &Square) -> bool {
+    let rank = sq.relative_rank(color);
+    match piece {
+        Piece::Pawn | Piece::Lance => rank == 1,
+        Piece::Knight => rank <= 2,
+        _ => false,
+    }
+}
+
+fn has_pawn_on_file(board: &Board, color: Color, file: u8) -> bool {
+    let pawns = board.piece_bb[Piece::Pawn.to_usize()] & board.occupied[color.to_usize()];
+    pawns.file_count_ones(file) > 0
+}
+
+pub(crate) fn validate_travel(board: &Board, from: &Square, mv: &Move) -> Result<()> {
+    let color = mv.color;
+    let prev_piece = board
+        .piece_type_at(from, color)
+        .ok_or_else(|| InvalidMove(format!("{:?} has no piece at {}", color, from)))?;
+    if board.occupied[color.to_usize()].is_filled(&mv.to) {
+        return Err(InvalidMove(format!(
+            "{:?} already has a piece on {:?}",
+            color, mv.to
+        )));
+    }
+    if prev_piece != mv.piece {
+        if !prev_piece.is_valid_promotion(&mv.piece) {
+            return Err(InvalidMove(format!(
+                "Invalid promotion: {:?} => {:?}",
+                prev_piece, mv.piece
+            )));
+        }
+        if !can_promote(color, from, &mv.to) {
+            return Err(InvalidMove(format!(
+                "{:?} can not promote at {:?} nor {:?}",
+                color, from, mv.to
+            )));
+        }
+    } else if is_dead_square(color, mv.piece, &mv.to) {
+        return Err(InvalidMove(format!(
+            "{:?} must promote on {:?}, where it could never move again",
+            mv.piece, mv.to
+        )));
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_drop(board: &Board, mv: &Move) -> Result<()> {
+    let occupied = board.occupied[0] | board.occupied[1];
+    if occupied.is_filled(&mv.to) {
+        return Err(InvalidMove(format!(
+            "{:?} can not be dropped on {:?}, which is occupied",
+            mv.piece, mv.to
+        )));
+    }
+    if mv.piece.is_promoted() || mv.piece == Piece::King || mv.piece == Piece::None {
+        return Err(InvalidMove(format!("{:?} can not be dropped", mv.piece)));
+    }
+    if is_dead_square(mv.color, mv.piece, &mv.to) {
+        return Err(InvalidMove(format!(
+            "{:?} dropped on {:?} would never be able to move",
+            mv.piece, mv.to
+        )));
+    }
+    if mv.piece == Piece::Pawn && has_pawn_on_file(board, mv.color, mv.to.file()) {
+        return Err(InvalidMove(format!(
+            "{:?} already has a pawn on file {} (nifu)",
+            mv.color,
+            mv.to.file()
+        )));
+    }
+    Ok(())
+}
+
+/// Returns true if `mv` leaves the own king attacked, where `pinned` are the pinned pieces of
+/// the moving side. Only evasions need to make the move on a copy of the board.
+fn leaves_king_in_check(board: &Board, mv: &Move, pinned: Bitboard, in_check: bool) -> bool {
+    let king = match board.king_square(mv.color) {
+        Some(king) => king,
+        None => return false,
+    };
+    if in_check {
+        let mut next = board.clone();
+        return match next.apply_move(*mv) {
+            Ok(_) => next.in_check(mv.color),
+            Err(_) => true,
+        };
+    }
+    match mv.from {
+        None => false,
+        Some(from) if from == king => {
+            let mut occupied = board.occupied[0] | board.occupied[1];
+            occupied.remove(&from);
+            !board
+                .attackers_to_occupied(&mv.to, mv.color.opponent(), occupied)
+                .is_empty()
+        }
+        Some(from) => pinned.is_filled(&from) && !Bitboard::line(&king, &from).is_filled(&mv.to),
+    }
+}
+
+/// Returns true if `mv` drops a pawn which checkmates the opponent.
+pub(crate) fn is_uchifuzume(board: &Board, mv: &Move) -> bool {
+    if mv.from.is_some() || mv.piece != Piece::Pawn {
+        return false;
+    }
+    let opponent = mv.color.opponent();
+    let checks = match board.king_square(opponent) {
+        Some(king) => {
+            king.file() == mv.to.file()
+                && match mv.color {
+                    Color::Black => king.rank() + 1 == mv.to.rank(),
+                    Color::White => king.rank() == mv.to.rank() + 1,
+                }
+        }
+        None => false,
+    };
+    if !checks {
+        return false;
+    }
+
+    let mut next = board.clone();
+    if next.apply_move(*mv).is_err() {
+        return false;
+    }
+    let mut replies = MoveList::new();
+    generate_legal_moves(&next, opponent, &mut replies);
+    replies.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SfenBoard;
+
+    fn count_moves(sfen: &str, color: Color) -> usize {
+        let board = SfenBoard::parse(sfen).unwrap().board;
+        board.generate_legal_moves(color).len()
+    }
+
+    #[test]
+    fn test_initial_moves() {
+        let board = Board::default();
+        assert_eq!(board.generate_legal_moves(Color::Black).len(), 30);
+        assert_eq!(board.generate_legal_moves(Color::White).len(), 30);
+
+        let mut moves = MoveList::new();
+        board.generate_legal_moves_into(Color::Black, &mut moves);
+        assert_eq!(moves.len(), 30);
+        assert!(moves.iter().all(|m| m.mv.color == Color::Black));
+    }
+
+    #[test]
+    fn test_many_candidates() {
+        // Far more candidate moves than any real position, which must not overflow the list.
+        let sfen = "8k/9/9/BBBBBBBBB/9/9/9/9/K8 b RBGSNLP 1";
+        let board = SfenBoard::parse(sfen).unwrap().board;
+        assert!(!board.generate_legal_moves(Color::Black).is_empty());
+    }
+
+    #[test]
+    fn test_pinned_piece() {
+        // The black knight on 5g is pinned by the white rook on 5a.
+        let sfen = "4r4/9/9/9/9/9/4N4/9/4K4 b - 1";
+        let board = SfenBoard::parse(sfen).unwrap().board;
+        let moves = board.generate_legal_moves(Color::Black);
+        assert!(moves.iter().all(|m| m.mv.from != Some(Square::at(5, 7))));
+        assert_eq!(moves.len(), 5);
+    }
+
+    #[test]
+    fn test_uchifuzume() {
+        let is_dropped_on = |moves: &[LegalMove], to: &Square| {
+            moves
+                .iter()
+                .any(|m| m.mv.from.is_none() && m.mv.piece == Piece::Pawn && &m.mv.to == to)
+        };
+        let to = Square::at(1, 2);
+
+        // The white gold on 2b could take the pawn on 1b if it was not pinned by the bishop.
+        let sfen = "8k/6Gg1/9/7N1/4B4/9/9/9/9 b P 1";
+        let board = SfenBoard::parse(sfen).unwrap().board;
+        let moves = board.generate_legal_moves(Color::Black);
+        assert!(!is_dropped_on(&moves, &to));
+        assert!(is_dropped_on(&moves, &Square::at(1, 3)));
+
+        let mut pushed = board.clone();
+        let drop = Move {
+            color: Color::Black,
+            from: None,
+            to,
+            piece: Piece::Pawn,
+        };
+        assert!(pushed.push_move(drop).is_err());
+
+        let sfen = "8k/6Gg1/9/7N1/9/9/9/9/9 b P 1";
+        let board = SfenBoard::parse(sfen).unwrap().board;
+        let moves = board.generate_legal_moves(Color::Black);
+        assert!(is_dropped_on(&moves, &to));
+
+        // The same pattern for White.
+        let sfen = "9/9/9/9/4b4/1n7/9/1Gg6/K8 w p 1";
+        let board = SfenBoard::parse(sfen).unwrap().board;
+        let moves = board.generate_legal_moves(Color::White);
+        assert!(!is_dropped_on(&moves, &Square::at(9, 8)));
+    }
+
+    #[test]
+    fn test_drop_restrictions() {
+        let drops = |sfen: &str, color: Color, piece: Piece| {
+            let board = SfenBoard::parse(sfen).unwrap().board;
+            board
+                .generate_legal_moves(color)
+                .into_iter()
+                .filter(|m| m.mv.from.is_none() && m.mv.piece == piece)
+                .map(|m| m.mv.to)
+                .collect::<Vec<_>>()
+        };
+
+        // Pawns, lances and knights may not be dropped where they could never move.
+        let moves = drops("9/9/9/9/9/9/9/9/9 b PLN 1", Color::Black, Piece::Pawn);
+        assert_eq!(moves.len(), 72);
+        assert!(moves.iter().all(|to| to.rank() != 1));
+        let moves = drops("9/9/9/9/9/9/9/9/9 b PLN 1", Color::Black, Piece::Lance);
+        assert_eq!(moves.len(), 72);
+        let moves = drops("9/9/9/9/9/9/9/9/9 w pln 1", Color::White, Piece::Knight);
+        assert_eq!(moves.len(), 63);
+        assert!(moves.iter().all(|to| to.rank() <= 7));
+
+        // Nifu only looks at unpromoted pawns of the same color.
+        let moves = drops("9/9/9/4p4/9/9/8+P/9/P8 b P 1", Color::Black, Piece::Pawn);
+        assert!(moves.iter().all(|to| to.file() != 9));
+        assert!(moves.iter().any(|to| to.file() == 5));
+        assert!(moves.iter().any(|to| to.file() == 1));
+        assert_eq!(moves.len(), 8 * 8 - 2);
+
+        let mut board = SfenBoard::parse("9/9/9/9/9/9/9/9/P8 b P 1").unwrap().board;
+        let drop = |file, rank| Move {
+            color: Color::Black,
+            from: None,
+            to: Square::at(file, rank),
+            piece: Piece::Pawn,
+        };
+        assert!(board.push_move(drop(9, 5)).is_err());
+        assert!(board.push_move(drop(8, 1)).is_err());
+        assert!(board.push_move(drop(9, 9)).is_err());
+        assert!(board.push_move(drop(8, 5)).is_ok());
+    }
+
+    #[test]
+    fn test_promotion() {
+        let travels = |sfen: &str, color: Color, from: Square| {
+            let board = SfenBoard::parse(sfen).unwrap().board;
+            board
+                .generate_legal_moves(color)
+                .into_iter()
+                .filter(|m| m.mv.from.as_ref() == Some(&from))
+                .map(|m| (m.mv.to.rank(), m.promoted))
+                .collect::<Vec<_>>()
+        };
+
+        // A pawn reaching the last rank must promote.
+        let moves = travels("9/4P4/9/9/9/9/9/9/9 b - 1", Color::Black, Square::at(5, 2));
+        assert_eq!(moves, [(1, true)]);
+        let moves = travels("9/9/9/9/9/9/9/4p4/9 w - 1", Color::White, Square::at(5, 8));
+        assert_eq!(moves, [(9, true)]);
+
+        // A knight must promote on the last two ranks.
+        let moves = travels("9/9/9/9/9/9/4n4/9/9 w - 1", Color::White, Square::at(5, 7));
+        assert_eq!(moves, [(9, true), (9, true)]);
+        let moves = travels("9/9/9/9/9/9/9/4n4/9 w - 1", Color::White, Square::at(5, 8));
+        assert!(moves.is_empty());
+
+        // A lance may stop unpromoted on its third rank but not on its first.
+        let mut moves = travels("9/9/9/9/9/9/9/9/4L4 b - 1", Color::Black, Square::at(5, 9));
+        moves.sort();
+        assert_eq!(
+            moves,
+            [
+                (1, true),
+                (2, false),
+                (2, true),
+                (3, false),
+                (3, true),
+                (4, false),
+                (5, false),
+                (6, false),
+                (7, false),
+                (8, false)
+            ]
+        );
+
+        // White silvers promote only in White's zone.
+        let moves = travels("9/9/4s4/9/9/9/9/9/9 w - 1", Color::White, Square::at(5, 3));
+        assert!(moves.iter().all(|&(_, promoted)| !promoted));
+
+        let mut board = SfenBoard::parse("9/4P4/9/9/9/9/9/9/9 b - 1").unwrap().board;
+        let from = Square::at(5, 2);
+        let to = Square::at(5, 1);
+        assert!(board.move_between(&from, &to, false, Color::Black).is_err());
+        let unpromoted = Move {
+            color: Color::Black,
+            from: Some(from),
+            to,
+            piece: Piece::Pawn,
+        };
+        assert!(board.push_move(unpromoted).is_err());
+        assert!(board.move_between(&from, &to, true, Color::Black).is_ok());
+
+        let mut board = SfenBoard::parse("9/9/9/9/4P4/9/9/9/9 b - 1").unwrap().board;
+        let from = Square::at(5, 5);
+        let to = Square::at(5, 4);
+        assert!(board.move_between(&from, &to, true, Color::Black).is_err());
+    }
+
+    #[test]
+    fn test_staged_generation() {
+        let sort = |moves: &[LegalMove]| {
+            let mut moves = moves
+                .iter()
+                .map(|m| {
+                    let from = m.mv.from.map(|sq| (sq.file(), sq.rank()));
+                    (from, m.mv.to.file(), m.mv.to.rank(), m.mv.piece.to_byte())
+                })
+                .collect::<Vec<_>>();
+            moves.sort();
+            moves
+        };
+
+        let sfen = "l6nl/5+P1gk/2np1S3/p1p4Pp/3P2Sp1/1PPb2P1P/P5GS1/R8/LN4bKL w RGgsn5p 1";
+        let board = SfenBoard::parse(sfen).unwrap().board;
+        let all = board.generate_legal_moves(Color::White);
+        let mut captures = MoveList::new();
+        generate_captures(&board, Color::White, &mut captures);
+        let mut quiets = MoveList::new();
+        generate_quiets(&board, Color::White, &mut quiets);
+        assert!(!captures.is_empty());
+        assert_eq!(captures.len() + quiets.len(), all.len());
+        let mut staged = captures.to_vec();
+        staged.extend_from_slice(&quiets);
+        assert_eq!(sort(&staged), sort(&all));
+        let black = board.occupied[Color::Black.to_usize()];
+        assert!(captures.iter().all(|m| black.is_filled(&m.mv.to)));
+
+        // Checks match the legal moves which leave the opponent in check.
+        let gives_check = |board: &Board, color: Color| {
+            let checks = board
+                .generate_legal_moves(color)
+                .into_iter()
+                .filter(|mv| {
+                    let mut next = board.clone();
+                    next.push_move(mv.mv).unwrap();
+                    next.in_check(color.opponent())
+                })
+                .collect::<Vec<_>>();
+            let mut staged = MoveList::new();
+            generate_checks(board, color, &mut staged);
+            assert_eq!(sort(&staged), sort(&checks));
+            staged.len()
+        };
+        assert!(gives_check(&board, Color::White) > 0);
+        assert!(gives_check(&board, Color::Black) > 0);
+
+        // The knight on 5c and the pawn on 3c uncover the rook and the bishop whether they
+        // promote or not, and a pawn dropped on 5b checks directly.
+        let sfen = "4k4/9/4N1P2/9/8B/9/9/9/4R3K b P 1";
+        let board = SfenBoard::parse(sfen).unwrap().board;
+        assert_eq!(gives_check(&board, Color::Black), 2 + 2 + 1);
+        assert_eq!(gives_check(&Board::default(), Color::Black), 0);
+
+        let mut evasions = MoveList::new();
+        generate_evasions(&board, Color::White, &mut evasions);
+        assert!(evasions.is_empty());
+
+        // The black king is checked by the rook and can block with drops.
+        let board = SfenBoard::parse("4r4/9/9/9/9/9/9/9/4K4 b G 1")
+            .unwrap()
+            .board;
+        generate_evasions(&board, Color::Black, &mut evasions);
+        assert_eq!(
+            sort(&evasions),
+            sort(&board.generate_legal_moves(Color::Black))
+        );
+        assert_eq!(evasions.len(), 4 + 7);
+    }
+
+    #[test]
+    fn test_no_king_capture() {
+        // White is left in check, but the king on 5a is never offered for capture.
+        let board = SfenBoard::parse("4k4/4R4/9/9/9/9/9/9/4K4 b - 1")
+            .unwrap()
+            .board;
+        let moves = board.generate_legal_moves(Color::Black);
+        assert!(!moves.is_empty());
+        for mv in moves {
+            assert_ne!(mv.mv.to, Square::at(5, 1));
+            board.clone().push_move(mv.mv).unwrap();
         }
+        let mut checks = MoveList::new();
+        generate_checks(&board, Color::Black, &mut checks);
+        assert!(checks.iter().all(|mv| mv.mv.to != Square::at(5, 1)));
+    }
+
+    #[test]
+    fn test_king_does_not_walk_into_check() {
+        // The white king on 5a may not step onto the 6th file covered by the black rook.
+        assert_eq!(count_moves("4k4/9/9/9/9/9/9/9/3R5 w - 1", Color::White), 3);
+        assert_eq!(count_moves("4k4/9/9/9/9/9/9/9/3R5 b - 1", Color::Black), 19);
     }
-    moves
 }
@@ -1,13 +1,25 @@
 use crate::error::ShogiUtilError::InvalidMove;
 use crate::piece::Piece;
-use crate::{Color, Move, Result, Square};
+use crate::{Color, ColoredPiece, Move, Result, Square};
+use std::fmt;
 
 mod bitboard;
+mod geometry;
+mod hand;
 mod legal_move;
-use legal_move::generate_legal_moves;
+mod move_list;
+mod perft;
+mod see;
+mod validate;
+mod zobrist;
+use legal_move::{generate_legal_moves, is_uchifuzume, validate_drop, validate_travel};
 
 use crate::model::LegalMove;
 pub use bitboard::Bitboard;
+pub use geometry::Direction;
+pub use hand::Hand;
+pub use move_list::{MoveList, MAX_MOVES};
+pub use validate::BoardProblem;
 
 const fn bit(file: u8, rank: u8) -> u128 {
     1 << ((rank - 1) * 9 + 9 - file)
@@ -37,16 +49,39 @@ const fn bit_file(file: u8) -> u128 {
         | bit(file, 9)
 }
 
-const PIECE_TYPES: usize = 15;
+pub(crate) const PIECE_TYPES: usize = 15;
 
+const SLIDERS: [Piece; 5] = [
+    Piece::Lance,
+    Piece::Bishop,
+    Piece::Rook,
+    Piece::ProBishop,
+    Piece::ProRook,
+];
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum GameStatus {
+    Ongoing,
+    Checkmated,
+    NoLegalMoves,
+}
+
+/// What `push_move` did, which is enough to take the move back with `undo_move`.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
 pub struct MoveResult {
+    pub mv: Move,
     pub promoted: bool,
+    pub captured: Option<Piece>,
 }
 
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct Board {
-    pub piece_bb: [Bitboard; PIECE_TYPES],
-    pub pieces_in_hand: [[u8; PIECE_TYPES]; 2],
-    pub occupied: [Bitboard; 2],
+    piece_bb: [Bitboard; PIECE_TYPES],
+    pieces_in_hand: [[u8; PIECE_TYPES]; 2],
+    occupied: [Bitboard; 2],
+    hash_key: u64,
+    /// The piece on each square, kept in sync with `piece_bb` and `occupied` for O(1) lookup.
+    mailbox: [Option<ColoredPiece>; 81],
 }
 
 impl Default for Board {
@@ -73,11 +108,16 @@ impl Default for Board {
             Bitboard(bit_rank(3) | bit(8, 2) | bit(2, 2) | bit_rank(1)),
         ];
         let pieces_in_hand = [[0; 15]; 2];
-        Self {
+        let mut board = Self {
             piece_bb,
             pieces_in_hand,
             occupied,
-        }
+            hash_key: 0,
+            mailbox: [None; 81],
+        };
+        board.hash_key = board.compute_hash_key();
+        board.mailbox = board.compute_mailbox();
+        board
     }
 }
 
@@ -87,6 +127,69 @@ impl Board {
             piece_bb: [Bitboard(0); PIECE_TYPES],
             pieces_in_hand: [[0; PIECE_TYPES]; 2],
             occupied: [Bitboard(0); 2],
+            hash_key: 0,
+            mailbox: [None; 81],
+        }
+    }
+
+    /// Returns the squares holding `piece` of either color.
+    pub fn piece_bb(&self, piece: Piece) -> Bitboard {
+        self.piece_bb[piece.to_usize()]
+    }
+
+    /// Returns the squares holding the pieces of `color`.
+    pub fn occupied(&self, color: Color) -> Bitboard {
+        self.occupied[color.to_usize()]
+    }
+
+    /// Returns the Zobrist key of this position. The side-to-move component is toggled by
+    /// every `push_move`, so a board which Black is to move on is keyed as such.
+    pub fn hash_key(&self) -> u64 {
+        self.hash_key
+    }
+
+    /// Computes the Zobrist key from scratch, assuming Black is to move.
+    pub fn compute_hash_key(&self) -> u64 {
+        let mut key = 0;
+        for (piece_id, &piece_bb) in self.piece_bb.iter().enumerate() {
+            for (color, &occupied) in self.occupied.iter().enumerate() {
+                for pos in (piece_bb & occupied).iter() {
+                    key ^= zobrist::piece_key(color, piece_id, pos as u8);
+                }
+            }
+        }
+        for (color, hand) in self.pieces_in_hand.iter().enumerate() {
+            for piece in Piece::HAND.iter() {
+                for i in 0..hand[piece.to_usize()] {
+                    key ^= zobrist::hand_key(color, piece.to_usize(), i);
+                }
+            }
+        }
+        key
+    }
+
+    /// Builds the piece on each square from `piece_bb` and `occupied`.
+    fn compute_mailbox(&self) -> [Option<ColoredPiece>; 81] {
+        let mut mailbox = [None; 81];
+        for (piece, &piece_bb) in Piece::ALL.iter().zip(self.piece_bb.iter()) {
+            for &color in [Color::Black, Color::White].iter() {
+                for sq in (piece_bb & self.occupied[color.to_usize()]).squares() {
+                    mailbox[sq.index()] = ColoredPiece::new(color, *piece);
+                }
+            }
+        }
+        mailbox
+    }
+
+    pub(crate) fn toggle_side(&mut self) {
+        self.hash_key ^= zobrist::SIDE_KEY;
+    }
+
+    /// Recomputes the hash key for a position which `color` is to move on.
+    pub(crate) fn set_side_to_move(&mut self, color: Color) {
+        self.hash_key = self.compute_hash_key();
+        if color == Color::White {
+            self.toggle_side();
         }
     }
 
@@ -97,85 +200,128 @@ impl Board {
         promote: bool,
         color: Color,
     ) -> Result<()> {
-        let piece = self.remove_piece(from, color)?;
-        if self.occupied[color.opponent().to_usize()].is_filled(to) {
-            let caught = self.remove_piece(to, color.opponent())?;
-            self.push_hand(caught, color);
-        }
-
-        if promote {
-            let piece = piece
+        let piece = self
+            .piece_type_at(from, color)
+            .ok_or_else(|| InvalidMove(format!("There's no piece at {}", from)))?;
+        let piece = if promote {
+            piece
                 .promote()
-                .ok_or_else(|| InvalidMove(format!("{:?} can not promote", piece)))?;
-            self.push_piece(to, color, piece);
+                .ok_or_else(|| InvalidMove(format!("{:?} can not promote", piece)))?
         } else {
-            self.push_piece(to, color, piece);
-        }
-
+            piece
+        };
+        self.push_move(Move {
+            color,
+            from: Some(*from),
+            to: *to,
+            piece,
+        })?;
         Ok(())
     }
 
     pub fn push_move(&mut self, mv: Move) -> Result<MoveResult> {
+        match mv.from.as_ref() {
+            Some(from) => validate_travel(self, from, &mv)?,
+            None => validate_drop(self, &mv)?,
+        }
+        if is_uchifuzume(self, &mv) {
+            return Err(InvalidMove(format!(
+                "Dropping a pawn on {:?} is checkmate (uchifuzume)",
+                mv.to
+            )));
+        }
+        self.apply_move(mv)
+    }
+
+    pub(crate) fn apply_move(&mut self, mv: Move) -> Result<MoveResult> {
         let color = mv.color;
         let piece = mv.piece;
         let to = mv.to;
 
+        if self.occupied[color.to_usize()].is_filled(&to) {
+            return Err(InvalidMove(format!(
+                "{:?} already has a piece on {:?}",
+                color, to
+            )));
+        }
+        let opponent = color.opponent();
+        let captured = self.piece_type_at(&to, opponent);
+        if captured == Some(Piece::King) {
+            return Err(InvalidMove(format!(
+                "The king on {:?} can not be captured",
+                to
+            )));
+        }
+
         let prev_piece;
         if let Some(from) = mv.from.as_ref() {
-            let maybe_prev_piece = self.remove_piece(from, color)?;
-            if maybe_prev_piece != piece {
-                if !maybe_prev_piece.is_valid_promotion(&piece) {
-                    return Err(InvalidMove(format!(
-                        "Invalid promotion: {:?} => {:?}",
-                        maybe_prev_piece, piece
-                    )));
-                }
-                assert!(piece.is_promoted());
-                let can_promote = match color {
-                    Color::Black => from.rank <= 3 || to.rank <= 3,
-                    Color::White => from.rank >= 7 || to.rank >= 7,
-                };
-                if !can_promote {
-                    return Err(InvalidMove(format!(
-                        "{:?} can not promote at {:?} nor {:?}",
-                        color, from, to
-                    )));
-                }
-            }
-            prev_piece = maybe_prev_piece;
+            prev_piece = self.remove_piece(from, color)?;
         } else {
             self.remove_hand(color, piece)?;
             assert!(!piece.is_promoted());
             prev_piece = piece;
         }
 
-        // todo validate move
-
-        let opponent = color.opponent();
-        if self.occupied[opponent.to_usize()].is_filled(&to) {
+        if captured.is_some() {
             let opponent_piece = self.remove_piece(&to, opponent)?;
-            self.push_hand(opponent_piece, color);
-        }
-        if self.occupied[color.to_usize()].is_filled(&to) {
-            return Err(InvalidMove(format!(
-                "{:?} already has a piece on {:?}",
-                color, to
-            )));
+            self.push_hand(opponent_piece, color)?;
         }
 
-        self.push_piece(&to, color, piece);
+        self.push_piece(&to, color, piece)?;
+        self.toggle_side();
         Ok(MoveResult {
             promoted: piece != prev_piece,
+            captured,
+            mv,
         })
     }
 
-    pub fn push_hand(&mut self, piece: Piece, color: Color) {
-        let piece = piece.revert_promotion().unwrap_or(piece);
-        assert!(!piece.is_promoted());
+    /// Takes back the move which returned `result`, restoring hands and the hash key.
+    pub fn undo_move(&mut self, result: &MoveResult) -> Result<()> {
+        let mv = &result.mv;
+        let color = mv.color;
+        let piece = self.remove_piece(&mv.to, color)?;
+        if piece != mv.piece {
+            return Err(InvalidMove(format!(
+                "Expected {:?} on {:?} but found {:?}",
+                mv.piece, mv.to, piece
+            )));
+        }
 
+        if let Some(captured) = result.captured {
+            let in_hand = captured.revert_promotion().unwrap_or(captured);
+            self.remove_hand(color, in_hand)?;
+            self.push_piece(&mv.to, color.opponent(), captured)?;
+        }
+        match mv.from.as_ref() {
+            Some(from) => {
+                let prev_piece = if result.promoted {
+                    piece.revert_promotion().unwrap_or(piece)
+                } else {
+                    piece
+                };
+                self.push_piece(from, color, prev_piece)?;
+            }
+            None => self.push_hand(piece, color)?,
+        }
+        self.toggle_side();
+        Ok(())
+    }
+
+    /// Adds `piece` to the hand of `color`, reverting its promotion.
+    pub fn push_hand(&mut self, piece: Piece, color: Color) -> Result<()> {
+        let piece = piece.revert_promotion().unwrap_or(piece);
+        let count = self.pieces_in_hand[color.to_usize()][piece.to_usize()];
+        if count as usize >= piece.max_piece_in_hand() {
+            return Err(InvalidMove(format!(
+                "{:?} can not hold another {:?}",
+                color, piece
+            )));
+        }
         let color = color.to_usize();
-        assert!(self.pieces_in_hand[color][piece.to_usize()] < piece.max_piece_in_hand() as u8);
         self.pieces_in_hand[color][piece.to_usize()] += 1;
+        self.hash_key ^= zobrist::hand_key(color, piece.to_usize(), count);
+        Ok(())
     }
 
     pub fn remove_hand(&mut self, color: Color, piece: Piece) -> Result<()> {
@@ -185,38 +331,174 @@ impl Board {
             Err(InvalidMove(format!("{:?} has no piece {:?}", color, piece)))
         } else {
             self.pieces_in_hand[color][piece] -= 1;
+            self.hash_key ^= zobrist::hand_key(color, piece, self.pieces_in_hand[color][piece]);
             Ok(())
         }
     }
 
     pub fn remove_piece(&mut self, sq: &Square, color: Color) -> Result<Piece> {
-        let color = color.to_usize();
-        if !self.occupied[color].is_filled(sq) {
-            return Err(InvalidMove(format!(
-                "There's no piece at {}{}",
-                sq.file, sq.rank
-            )));
-        }
+        let piece = self
+            .piece_type_at(sq, color)
+            .ok_or_else(|| InvalidMove(format!("There's no piece at {}", sq)))?;
 
-        let filled_piece_types = (0..PIECE_TYPES)
-            .filter(|&i| self.piece_bb[i].is_filled(sq))
-            .collect::<Vec<_>>();
-        assert_eq!(filled_piece_types.len(), 1);
-        let piece_type = filled_piece_types[0];
+        self.piece_bb[piece.to_usize()].remove(sq);
+        self.occupied[color.to_usize()].remove(sq);
+        self.mailbox[sq.index()] = None;
+        self.hash_key ^= zobrist::piece_key(color.to_usize(), piece.to_usize(), sq.index() as u8);
+        Ok(piece)
+    }
 
-        self.piece_bb[piece_type].remove(sq);
-        self.occupied[color].remove(sq);
-        Ok(Piece::from(piece_type as u8))
+    pub(crate) fn piece_type_at(&self, sq: &Square, color: Color) -> Option<Piece> {
+        match self.mailbox[sq.index()] {
+            Some(piece) if piece.color() == color => Some(piece.piece()),
+            _ => None,
+        }
     }
 
-    pub fn push_piece(&mut self, sq: &Square, color: Color, piece: Piece) {
-        assert!(!self.occupied[color.to_usize()].is_filled(sq));
+    /// Puts `piece` of `color` on `sq`, which must be empty.
+    pub fn push_piece(&mut self, sq: &Square, color: Color, piece: Piece) -> Result<()> {
+        if self.mailbox[sq.index()].is_some() {
+            return Err(InvalidMove(format!("{} is already occupied", sq)));
+        }
+        let colored = ColoredPiece::new(color, piece)
+            .ok_or_else(|| InvalidMove(format!("{:?} can not be put on the board", piece)))?;
         self.occupied[color.to_usize()].fill(sq);
         self.piece_bb[piece.to_usize()].fill(sq);
+        self.mailbox[sq.index()] = Some(colored);
+        self.hash_key ^= zobrist::piece_key(color.to_usize(), piece.to_usize(), sq.index() as u8);
+        Ok(())
+    }
+
+    /// Returns the owner and the kind of the piece on `sq`.
+    pub fn piece_at(&self, sq: &Square) -> Option<(Color, Piece)> {
+        self.mailbox[sq.index()].map(|piece| (piece.color(), piece.piece()))
     }
 
-    pub fn generate_legal_moves(&self) -> Vec<LegalMove> {
-        generate_legal_moves(self)
+    /// Iterates the pieces on the board in the order of `Square::ALL`.
+    pub fn pieces(&self) -> impl Iterator<Item = (Square, Color, Piece)> + '_ {
+        Square::ALL
+            .iter()
+            .filter_map(move |&sq| self.piece_at(&sq).map(|(color, piece)| (sq, color, piece)))
+    }
+
+    pub fn hand(&self, color: Color) -> Hand<'_> {
+        Hand::new(&self.pieces_in_hand[color.to_usize()])
+    }
+
+    pub fn king_square(&self, color: Color) -> Option<Square> {
+        let king_bb = self.piece_bb[Piece::King.to_usize()] & self.occupied[color.to_usize()];
+        king_bb.squares().next()
+    }
+
+    /// Returns the pieces of `color` which attack `sq`.
+    pub fn attackers_to(&self, sq: &Square, color: Color) -> Bitboard {
+        self.attackers_to_occupied(sq, color, self.occupied[0] | self.occupied[1])
+    }
+
+    /// Same as `attackers_to`, but only the pieces in `occupied` are on the board,
+    /// so removing a piece reveals the sliders behind it.
+    pub(crate) fn attackers_to_occupied(
+        &self,
+        sq: &Square,
+        color: Color,
+        occupied: Bitboard,
+    ) -> Bitboard {
+        // A piece of `color` attacks `sq` iff the same piece of the other color standing on
+        // `sq` attacks its square.
+        let mut attackers = Bitboard::empty();
+        for (piece_id, &piece_bb) in self.piece_bb.iter().enumerate().skip(1) {
+            let candidates = piece_bb & self.occupied[color.to_usize()] & occupied;
+            if candidates.is_empty() {
+                continue;
+            }
+            let piece = Piece::ALL[piece_id];
+            attackers = attackers | (piece.attacks(color.opponent(), sq, occupied) & candidates);
+        }
+        attackers
+    }
+
+    /// Returns the opponent pieces giving check to the king of `color`.
+    pub fn checkers(&self, color: Color) -> Bitboard {
+        match self.king_square(color) {
+            Some(king) => self.attackers_to(&king, color.opponent()),
+            None => Bitboard::empty(),
+        }
+    }
+
+    pub fn in_check(&self, color: Color) -> bool {
+        self.checkers(color).0 != 0
+    }
+
+    /// Returns the pieces of either color which are the only piece between the king of `color`
+    /// and an opponent slider.
+    pub fn blockers_for_king(&self, color: Color) -> Bitboard {
+        let king = match self.king_square(color) {
+            Some(king) => king,
+            None => return Bitboard::empty(),
+        };
+        let occupied = self.occupied[0] | self.occupied[1];
+        let opponent = self.occupied[color.opponent().to_usize()];
+        let mut blockers = Bitboard::empty();
+        for &piece in &SLIDERS {
+            // Opponent sliders which would attack the king on an empty board.
+            let snipers = self.piece_bb[piece.to_usize()]
+                & opponent
+                & piece.attacks(color, &king, Bitboard::empty());
+            for sniper in snipers.squares() {
+                let between = Bitboard::between(&king, &sniper) & occupied;
+                if between.0.count_ones() == 1 {
+                    blockers = blockers | between;
+                }
+            }
+        }
+        blockers
+    }
+
+    /// Returns the pieces of `color` which are pinned to their own king.
+    pub fn pinned_pieces(&self, color: Color) -> Bitboard {
+        self.blockers_for_king(color) & self.occupied[color.to_usize()]
+    }
+
+    pub fn generate_legal_moves(&self, color: Color) -> Vec<LegalMove> {
+        let mut moves = MoveList::new();
+        generate_legal_moves(self, color, &mut moves);
+        moves.to_vec()
+    }
+
+    /// Fills `moves` with the legal moves of `color` without allocating.
+    pub fn generate_legal_moves_into(&self, color: Color, moves: &mut MoveList) {
+        generate_legal_moves(self, color, moves);
+    }
+
+    pub fn generate_captures(&self, color: Color, moves: &mut MoveList) {
+        legal_move::generate_captures(self, color, moves);
+    }
+
+    pub fn generate_quiets(&self, color: Color, moves: &mut MoveList) {
+        legal_move::generate_quiets(self, color, moves);
+    }
+
+    pub fn generate_checks(&self, color: Color, moves: &mut MoveList) {
+        legal_move::generate_checks(self, color, moves);
+    }
+
+    pub fn generate_evasions(&self, color: Color, moves: &mut MoveList) {
+        legal_move::generate_evasions(self, color, moves);
+    }
+
+    pub fn is_checkmate(&self, color: Color) -> bool {
+        self.game_status(color) == GameStatus::Checkmated
+    }
+
+    /// Returns the status of this position with `color` to move.
+    pub fn game_status(&self, color: Color) -> GameStatus {
+        if !self.generate_legal_moves(color).is_empty() {
+            GameStatus::Ongoing
+        } else if self.in_check(color) {
+            GameStatus::Checkmated
+        } else {
+            GameStatus::NoLegalMoves
+        }
     }
 
     pub fn rotate180(&self) -> Self {
@@ -226,11 +508,75 @@ impl Board {
         }
         let occupied = [self.occupied[1].rotate180(), self.occupied[0].rotate180()];
         let pieces_in_hand = [self.pieces_in_hand[1], self.pieces_in_hand[0]];
-        Self {
+        let mut board = Self {
             piece_bb,
             pieces_in_hand,
             occupied,
+            hash_key: 0,
+            mailbox: [None; 81],
+        };
+        // Colors are swapped, and so is the side to move.
+        let side = self.hash_key ^ self.compute_hash_key();
+        board.hash_key = board.compute_hash_key() ^ side ^ zobrist::SIDE_KEY;
+        board.mailbox = board.compute_mailbox();
+        board
+    }
+
+    /// Flips files 1 and 9, keeping colors, hands and the side to move.
+    pub fn mirror(&self) -> Self {
+        let mut piece_bb = [Bitboard(0); PIECE_TYPES];
+        for (i, bb) in self.piece_bb.iter().enumerate() {
+            piece_bb[i] = bb.mirror();
+        }
+        let mut board = Self {
+            piece_bb,
+            pieces_in_hand: self.pieces_in_hand,
+            occupied: [self.occupied[0].mirror(), self.occupied[1].mirror()],
+            hash_key: 0,
+            mailbox: [None; 81],
+        };
+        let side = self.hash_key ^ self.compute_hash_key();
+        board.hash_key = board.compute_hash_key() ^ side;
+        board.mailbox = board.compute_mailbox();
+        board
+    }
+
+    /// Returns this board or its mirror, whichever has the smaller hash key, so that
+    /// symmetric positions share one form.
+    pub fn canonical(&self) -> Self {
+        let mirrored = self.mirror();
+        if mirrored.hash_key < self.hash_key {
+            mirrored
+        } else {
+            self.clone()
+        }
+    }
+}
+
+/// Renders the board in CSA format: rows `P1` to `P9` followed by the hands in `P+` and `P-`.
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for rank in 1..=9 {
+            write!(f, "P{}", rank)?;
+            for file in (1..=9).rev() {
+                match self.piece_at(&Square::at(file, rank)) {
+                    Some((Color::Black, piece)) => write!(f, "+{}", piece.to_csa())?,
+                    Some((Color::White, piece)) => write!(f, "-{}", piece.to_csa())?,
+                    None => write!(f, " * ")?,
+                }
+            }
+            writeln!(f)?;
         }
+        for &(sign, color) in [("+", Color::Black), ("-", Color::White)].iter() {
+            write!(f, "P{}", sign)?;
+            for (piece, count) in self.hand(color).iter() {
+                for _ in 0..count {
+                    write!(f, "00{}", piece.to_csa())?;
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
     }
 }
 
@@ -238,6 +584,7 @@ impl Board {
 mod tests {
     use super::*;
     use crate::debug::dump_board;
+    use crate::{SfenBoard, SfenMove};
 
     #[test]
     fn test_init_board() {
@@ -284,6 +631,277 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_check_detection() {
+        let board = Board::default();
+        assert!(!board.in_check(Color::Black));
+        assert!(!board.in_check(Color::White));
+
+        // The white king on 5a is checked by the pawn on 5b and the lance on 5i is blocked.
+        let board = SfenBoard::parse("4k4/4P4/9/9/9/9/9/9/4L4 w - 1")
+            .unwrap()
+            .board;
+        assert!(board.in_check(Color::White));
+        assert_eq!(board.checkers(Color::White).0, bit(5, 2));
+        assert!(!board.in_check(Color::Black));
+
+        // Knights and bishops attack 5e from both sides of the board.
+        let board = SfenBoard::parse("9/9/3n5/9/9/9/5N3/9/B7b b - 1")
+            .unwrap()
+            .board;
+        let sq = Square::at(5, 5);
+        assert_eq!(
+            board.attackers_to(&sq, Color::Black).0,
+            bit(9, 9) | bit(4, 7)
+        );
+        assert_eq!(
+            board.attackers_to(&sq, Color::White).0,
+            bit(1, 9) | bit(6, 3)
+        );
+    }
+
+    #[test]
+    fn test_pinned_pieces() {
+        // The silver on 5g and the gold on 3g are pinned, the white pawn on 6h blocks a
+        // discovered check, and two pieces stand between the king and the rook on 8i.
+        let board = SfenBoard::parse("4r4/9/9/9/8b/9/2b1S1G2/3p5/1rSGK4 b - 1")
+            .unwrap()
+            .board;
+        assert_eq!(
+            board.blockers_for_king(Color::Black).0,
+            bit(5, 7) | bit(3, 7) | bit(6, 8)
+        );
+        assert_eq!(board.pinned_pieces(Color::Black).0, bit(5, 7) | bit(3, 7));
+        assert!(board.pinned_pieces(Color::White).is_empty());
+    }
+
+    #[test]
+    fn test_game_status() {
+        let board = Board::default();
+        assert_eq!(board.game_status(Color::Black), GameStatus::Ongoing);
+
+        // The gold on 5b is protected by the pawn on 5c.
+        let board = SfenBoard::parse("4k4/4G4/4P4/9/9/9/9/9/9 w - 1")
+            .unwrap()
+            .board;
+        assert!(board.is_checkmate(Color::White));
+
+        // Without the pawn the king takes the gold.
+        let board = SfenBoard::parse("4k4/4G4/9/9/9/9/9/9/9 w - 1")
+            .unwrap()
+            .board;
+        assert_eq!(board.game_status(Color::White), GameStatus::Ongoing);
+
+        // A lone black lance on the first rank can never move.
+        let board = SfenBoard::parse("L8/9/9/9/9/9/9/9/9 b - 1").unwrap().board;
+        assert_eq!(board.game_status(Color::Black), GameStatus::NoLegalMoves);
+    }
+
+    #[test]
+    fn test_hash_key() {
+        let initial = Board::default();
+        assert_eq!(initial.hash_key(), initial.compute_hash_key());
+        assert_eq!(Board::empty().hash_key(), 0);
+
+        let travel = |board: &mut Board, color, from: (u8, u8), to: (u8, u8)| {
+            board
+                .move_between(
+                    &Square::at(from.0, from.1),
+                    &Square::at(to.0, to.1),
+                    false,
+                    color,
+                )
+                .unwrap();
+        };
+
+        // Transpositions share a key.
+        let mut a = Board::default();
+        travel(&mut a, Color::Black, (7, 7), (7, 6));
+        travel(&mut a, Color::White, (3, 3), (3, 4));
+        travel(&mut a, Color::Black, (2, 7), (2, 6));
+        let mut b = Board::default();
+        travel(&mut b, Color::Black, (2, 7), (2, 6));
+        travel(&mut b, Color::White, (3, 3), (3, 4));
+        travel(&mut b, Color::Black, (7, 7), (7, 6));
+        assert_eq!(a.hash_key(), b.hash_key());
+        assert_ne!(a.hash_key(), initial.hash_key());
+
+        // The same placement with the other side to move differs.
+        assert_eq!(a.hash_key() ^ a.compute_hash_key(), zobrist::SIDE_KEY);
+
+        // Captures and drops are tracked incrementally.
+        travel(&mut a, Color::White, (2, 2), (8, 8));
+        travel(&mut a, Color::Black, (7, 9), (8, 8));
+        a.push_move(Move {
+            color: Color::White,
+            from: None,
+            to: Square::at(4, 5),
+            piece: Piece::Bishop,
+        })
+        .unwrap();
+        assert_eq!(a.hash_key(), a.compute_hash_key());
+        assert_eq!(a.rotate180().rotate180().hash_key(), a.hash_key());
+    }
+
+    #[test]
+    fn test_piece_lookup() {
+        let board = Board::default();
+        assert_eq!(
+            board.piece_at(&Square::at(5, 9)),
+            Some((Color::Black, Piece::King))
+        );
+        assert_eq!(
+            board.piece_at(&Square::at(2, 2)),
+            Some((Color::White, Piece::Bishop))
+        );
+        assert_eq!(board.piece_at(&Square::at(5, 5)), None);
+        assert_eq!(board.pieces().count(), 40);
+        assert_eq!(
+            board.pieces().next(),
+            Some((Square::at(9, 1), Color::White, Piece::Lance))
+        );
+        assert!(board.hand(Color::Black).is_empty());
+
+        let board = SfenBoard::parse("4k4/9/9/9/9/9/9/9/4K4 b 2SP3p 1")
+            .unwrap()
+            .board;
+        let hand = board.hand(Color::Black);
+        assert_eq!(hand.count(Piece::Silver), 2);
+        assert!(hand.contains(Piece::Pawn));
+        assert!(!hand.contains(Piece::Gold));
+        assert_eq!(hand.len(), 3);
+        assert_eq!(
+            hand.iter().collect::<Vec<_>>(),
+            vec![(Piece::Pawn, 1), (Piece::Silver, 2)]
+        );
+        assert_eq!(board.hand(Color::White).len(), 3);
+    }
+
+    #[test]
+    fn test_mailbox() {
+        let in_sync = |board: &Board| {
+            assert_eq!(board.mailbox, board.compute_mailbox());
+            for sq in Square::ALL.iter() {
+                let expected = Piece::ALL.iter().skip(1).find_map(|&piece| {
+                    [Color::Black, Color::White]
+                        .iter()
+                        .find(|&&color| {
+                            (board.piece_bb(piece) & board.occupied(color)).is_filled(sq)
+                        })
+                        .map(|&color| (color, piece))
+                });
+                assert_eq!(board.piece_at(sq), expected);
+            }
+        };
+
+        let mut board = Board::default();
+        let mut results = vec![];
+        // The bishops are exchanged on 2b and 8h with a promotion.
+        for mv in ["7g7f", "3c3d", "8h2b+", "3a2b"].iter() {
+            let mv = match SfenMove::parse(mv).unwrap() {
+                SfenMove::Travel { from, to, promoted } => (from, to, promoted),
+                SfenMove::DropMove { .. } => unreachable!(),
+            };
+            let color = board.piece_at(&mv.0).unwrap().0;
+            let piece = board.piece_type_at(&mv.0, color).unwrap();
+            let piece = if mv.2 {
+                piece.promote().unwrap()
+            } else {
+                piece
+            };
+            let result = board
+                .push_move(Move {
+                    color,
+                    from: Some(mv.0),
+                    to: mv.1,
+                    piece,
+                })
+                .unwrap();
+            in_sync(&board);
+            results.push(result);
+        }
+        assert_eq!(results[2].captured, Some(Piece::Bishop));
+        assert_eq!(results[3].captured, Some(Piece::ProBishop));
+        assert_eq!(
+            board.piece_at(&Square::at(2, 2)),
+            Some((Color::White, Piece::Silver))
+        );
+        for result in results.iter().rev() {
+            board.undo_move(result).unwrap();
+            in_sync(&board);
+        }
+        assert_eq!(board, Board::default());
+
+        // Mutators refuse what would put the board out of sync.
+        assert!(board
+            .push_piece(&Square::at(5, 9), Color::White, Piece::Gold)
+            .is_err());
+        assert!(board
+            .push_piece(&Square::at(5, 5), Color::Black, Piece::None)
+            .is_err());
+        assert!(board.push_hand(Piece::King, Color::Black).is_err());
+        assert!(board.remove_piece(&Square::at(5, 9), Color::White).is_err());
+        assert_eq!(board, Board::default());
+        board.push_hand(Piece::ProRook, Color::Black).unwrap();
+        assert_eq!(board.hand(Color::Black).count(Piece::Rook), 1);
+        assert_eq!(board.rotate180().hand(Color::White).count(Piece::Rook), 1);
+    }
+
+    #[test]
+    fn test_mirror() {
+        let board = Board::default();
+        assert_ne!(board.mirror(), board);
+        assert_eq!(board.mirror().mirror(), board);
+        assert_eq!(board.mirror().hash_key(), board.mirror().compute_hash_key());
+
+        let left = SfenBoard::parse("4k4/9/9/9/9/9/2P6/9/4K4 w 2Sp 1").unwrap();
+        let right = SfenBoard::parse("4k4/9/9/9/9/9/6P2/9/4K4 w 2Sp 1").unwrap();
+        assert_eq!(left.board.mirror(), right.board);
+        assert_eq!(left.board.mirror().hash_key(), right.board.hash_key());
+        assert_eq!(left.board.canonical(), right.board.canonical());
+        assert_eq!(
+            left.board.canonical().hash_key(),
+            std::cmp::min(left.board.hash_key(), right.board.hash_key())
+        );
+    }
+
+    #[test]
+    fn test_undo_move() {
+        let initial = Board::default();
+        let mut board = initial.clone();
+        let moves = [
+            (Color::Black, Some((7, 7)), (7, 6), Piece::Pawn),
+            (Color::White, Some((3, 3)), (3, 4), Piece::Pawn),
+            (Color::Black, Some((8, 8)), (2, 2), Piece::ProBishop),
+            (Color::White, Some((3, 1)), (2, 2), Piece::Silver),
+            (Color::Black, None, (4, 5), Piece::Bishop),
+        ];
+        let mut results = vec![];
+        let mut snapshots = vec![];
+        for &(color, from, to, piece) in moves.iter() {
+            snapshots.push(board.clone());
+            let result = board
+                .push_move(Move {
+                    color,
+                    from: from.map(|(file, rank)| Square::at(file, rank)),
+                    to: Square::at(to.0, to.1),
+                    piece,
+                })
+                .unwrap();
+            results.push(result);
+        }
+        assert_eq!(results[2].captured, Some(Piece::Bishop));
+        assert!(results[2].promoted);
+        assert_eq!(results[3].captured, Some(Piece::ProBishop));
+
+        while let Some(result) = results.pop() {
+            board.undo_move(&result).unwrap();
+            assert_eq!(board, snapshots.pop().unwrap());
+        }
+        assert_eq!(board, initial);
+        assert_eq!(board.hash_key(), initial.hash_key());
+    }
+
     #[test]
     fn test_generate_legal_moves() {
         let board = Board::default();
@@ -0,0 +1,121 @@
+use crate::{Color, LegalMove, Move, Piece, Square};
+use std::ops::Deref;
+
+/// Room for the 593 legal moves of the richest known position, with some margin. Generators
+/// push candidates before filtering them, so a list may still outgrow this on positions no game
+/// can reach, in which case it moves to the heap.
+pub const MAX_MOVES: usize = 600;
+
+const EMPTY_MOVE: LegalMove = LegalMove {
+    mv: Move {
+        color: Color::Black,
+        from: None,
+        to: Square::at(1, 1),
+        piece: Piece::None,
+    },
+    promoted: false,
+};
+
+/// A list of moves which lives on the stack unless it outgrows `MAX_MOVES`.
+#[derive(Clone)]
+pub struct MoveList {
+    moves: [LegalMove; MAX_MOVES],
+    len: usize,
+    /// Holds every move instead of `moves` once the list has outgrown it.
+    spilled: Vec<LegalMove>,
+}
+
+impl Default for MoveList {
+    fn default() -> Self {
+        Self {
+            moves: [EMPTY_MOVE; MAX_MOVES],
+            len: 0,
+            spilled: vec![],
+        }
+    }
+}
+
+impl MoveList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, mv: LegalMove) {
+        if !self.spilled.is_empty() {
+            self.spilled.push(mv);
+        } else if self.len < MAX_MOVES {
+            self.moves[self.len] = mv;
+            self.len += 1;
+        } else {
+            self.spilled.extend_from_slice(&self.moves[..self.len]);
+            self.spilled.push(mv);
+            self.len = 0;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+        self.spilled.clear();
+    }
+
+    pub fn retain<F: FnMut(&LegalMove) -> bool>(&mut self, mut f: F) {
+        if !self.spilled.is_empty() {
+            self.spilled.retain(|mv| f(mv));
+            return;
+        }
+        let mut len = 0;
+        for i in 0..self.len {
+            if f(&self.moves[i]) {
+                self.moves[len] = self.moves[i];
+                len += 1;
+            }
+        }
+        self.len = len;
+    }
+}
+
+impl Deref for MoveList {
+    type Target = [LegalMove];
+
+    fn deref(&self) -> &Self::Target {
+        if self.spilled.is_empty() {
+            &self.moves[..self.len]
+        } else {
+            &self.spilled
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_move_list() {
+        let mut list = MoveList::new();
+        assert!(list.is_empty());
+        for rank in 1..=9 {
+            let mut mv = EMPTY_MOVE;
+            mv.mv.to = Square::at(1, rank);
+            list.push(mv);
+        }
+        assert_eq!(list.len(), 9);
+        list.retain(|m| m.mv.to.rank() % 2 == 0);
+        let ranks = list.iter().map(|m| m.mv.to.rank()).collect::<Vec<_>>();
+        assert_eq!(ranks, [2, 4, 6, 8]);
+        list.clear();
+        assert!(list.is_empty());
+
+        for i in 0..MAX_MOVES + 10 {
+            let mut mv = EMPTY_MOVE;
+            mv.mv.to = Square::ALL[i % 81];
+            list.push(mv);
+        }
+        assert_eq!(list.len(), MAX_MOVES + 10);
+        assert_eq!(list[MAX_MOVES + 9].mv.to, Square::ALL[(MAX_MOVES + 9) % 81]);
+        list.retain(|m| m.mv.to == Square::ALL[0]);
+        assert_eq!(list.len(), 8);
+        list.clear();
+        assert!(list.is_empty());
+    }
+}
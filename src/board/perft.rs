@@ -0,0 +1,152 @@
+use super::legal_move::generate_legal_moves;
+use super::MoveList;
+use crate::{Board, Color, LegalMove, Result};
+use std::collections::HashMap;
+
+impl Board {
+    /// Counts the leaf nodes of the legal move tree of `depth` plies with `color` to move.
+    /// Fails if a generated move can not be played, which only happens on broken boards.
+    pub fn perft(&self, color: Color, depth: usize) -> Result<u64> {
+        let mut board = self.clone();
+        perft(&mut board, color, depth, &mut None)
+    }
+
+    /// Same as `perft`, caching subtree counts in `table` by hash key and depth.
+    pub fn perft_with_table(
+        &self,
+        color: Color,
+        depth: usize,
+        table: &mut HashMap<(u64, usize), u64>,
+    ) -> Result<u64> {
+        let mut board = self.clone();
+        perft(&mut board, color, depth, &mut Some(table))
+    }
+
+    /// Returns the perft count below each legal move of `color`.
+    pub fn divide(&self, color: Color, depth: usize) -> Result<Vec<(LegalMove, u64)>> {
+        assert!(depth > 0);
+        let mut board = self.clone();
+        let mut moves = MoveList::new();
+        generate_legal_moves(&board, color, &mut moves);
+        moves
+            .iter()
+            .map(|&mv| {
+                let result = board.apply_move(mv.mv)?;
+                let count = perft(&mut board, color.opponent(), depth - 1, &mut None)?;
+                board.undo_move(&result)?;
+                Ok((mv, count))
+            })
+            .collect()
+    }
+}
+
+fn perft(
+    board: &mut Board,
+    color: Color,
+    depth: usize,
+    table: &mut Option<&mut HashMap<(u64, usize), u64>>,
+) -> Result<u64> {
+    if depth == 0 {
+        return Ok(1);
+    }
+    if let Some(&count) = table
+        .as_ref()
+        .and_then(|t| t.get(&(board.hash_key(), depth)))
+    {
+        return Ok(count);
+    }
+
+    let mut moves = MoveList::new();
+    generate_legal_moves(board, color, &mut moves);
+    let count = if depth == 1 {
+        moves.len() as u64
+    } else {
+        let mut count = 0;
+        for mv in moves.iter() {
+            let result = board.apply_move(mv.mv)?;
+            count += perft(board, color.opponent(), depth - 1, table)?;
+            board.undo_move(&result)?;
+        }
+        count
+    };
+
+    if let Some(table) = table.as_mut() {
+        table.insert((board.hash_key(), depth), count);
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SfenBoard;
+
+    #[test]
+    fn test_perft_initial_position() {
+        let board = Board::default();
+        assert_eq!(board.perft(Color::Black, 1).unwrap(), 30);
+        assert_eq!(board.perft(Color::Black, 2).unwrap(), 900);
+        assert_eq!(board.perft(Color::Black, 3).unwrap(), 25470);
+
+        let mut table = HashMap::new();
+        assert_eq!(
+            board.perft_with_table(Color::Black, 4, &mut table).unwrap(),
+            719731
+        );
+
+        let divided = board.divide(Color::Black, 3).unwrap();
+        assert_eq!(divided.len(), 30);
+        assert_eq!(divided.iter().map(|(_, n)| n).sum::<u64>(), 25470);
+    }
+
+    #[test]
+    fn test_perft_tricky_positions() {
+        let perft = |sfen: &str, depth: usize| {
+            let sfen_board = SfenBoard::parse(sfen).unwrap();
+            sfen_board.board.perft(sfen_board.next_turn, depth).unwrap()
+        };
+
+        // The position with the largest known number of legal moves.
+        assert_eq!(
+            perft("R8/2K1S1SSk/4B4/9/9/9/9/9/1L1L1L3 b RBGSNLP3g3n17p 1", 1),
+            593
+        );
+
+        // "Matsuri", which is full of pins, drops and promotions.
+        let matsuri = "l6nl/5+P1gk/2np1S3/p1p4Pp/3P2Sp1/1PPb2P1P/P5GS1/R8/LN4bKL w RGgsn5p 1";
+        assert_eq!(perft(matsuri, 1), 207);
+        assert_eq!(perft(matsuri, 2), 28684);
+    }
+
+    #[test]
+    fn test_perft_broken_positions() {
+        // White is left in check, and its king must not be captured.
+        let board = SfenBoard::parse("4k4/4R4/9/9/9/9/9/9/4K4 b - 1")
+            .unwrap()
+            .board;
+        assert_eq!(board.perft(Color::Black, 1).unwrap(), 33);
+        assert!(board.perft(Color::Black, 2).is_ok());
+        assert!(board.divide(Color::Black, 2).is_ok());
+
+        // Capturing the white pawn would give Black a 19th pawn in hand.
+        let board = SfenBoard::parse("4k4/9/4p4/4R4/9/9/9/9/4K4 b 18P 1")
+            .unwrap()
+            .board;
+        assert!(board.perft(Color::Black, 1).is_ok());
+        assert!(board.perft(Color::Black, 2).is_err());
+    }
+
+    #[test]
+    fn test_divide_uchifuzume() {
+        // Dropping a pawn on 1b mates, because the gold on 2b is pinned by the bishop.
+        let sfen_board = SfenBoard::parse("8k/6Gg1/9/7N1/4B4/9/9/9/9 b P 1").unwrap();
+        let divided = sfen_board.board.divide(Color::Black, 2).unwrap();
+        assert!(divided
+            .iter()
+            .all(|(m, _)| !(m.mv.from.is_none() && m.mv.to.file() == 1 && m.mv.to.rank() == 2)));
+        assert_eq!(
+            divided.iter().map(|(_, n)| n).sum::<u64>(),
+            sfen_board.board.perft(Color::Black, 2).unwrap()
+        );
+    }
+}
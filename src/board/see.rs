@@ -0,0 +1,209 @@
+use super::legal_move::{can_promote, validate_drop, validate_travel};
+use crate::{Bitboard, Board, Color, LegalMove, Piece, Result, Square};
+
+/// Every capture in an exchange removes a piece from the board, so there can be no more
+/// captures than squares, plus the move itself.
+const MAX_EXCHANGE: usize = 82;
+
+/// Pieces in the order of their values, which is the order to recapture with.
+const RECAPTURE_ORDER: [Piece; 14] = [
+    Piece::Pawn,
+    Piece::Lance,
+    Piece::Knight,
+    Piece::Silver,
+    Piece::ProPawn,
+    Piece::ProLance,
+    Piece::ProKnight,
+    Piece::ProSilver,
+    Piece::Gold,
+    Piece::Bishop,
+    Piece::ProBishop,
+    Piece::Rook,
+    Piece::ProRook,
+    Piece::King,
+];
+
+fn piece_value(piece: Piece) -> i32 {
+    match piece {
+        Piece::None => 0,
+        Piece::Pawn => 90,
+        Piece::Lance => 315,
+        Piece::Knight => 405,
+        Piece::Silver => 495,
+        Piece::Gold => 540,
+        Piece::Bishop => 855,
+        Piece::Rook => 990,
+        Piece::ProPawn | Piece::ProLance | Piece::ProKnight | Piece::ProSilver => 540,
+        Piece::ProBishop => 945,
+        Piece::ProRook => 1395,
+        Piece::King => 15000,
+    }
+}
+
+/// The swing of capturing `piece`: the opponent loses it and it goes into our hand unpromoted.
+fn capture_value(piece: Piece) -> i32 {
+    piece_value(piece) + piece_value(piece.revert_promotion().unwrap_or(piece))
+}
+
+fn promotion_value(piece: Piece) -> i32 {
+    piece
+        .promote()
+        .map_or(0, |p| piece_value(p) - piece_value(piece))
+}
+
+impl Board {
+    /// Static exchange evaluation: the material balance for the side making `mv` after the
+    /// best sequence of captures on its destination by both sides. Fails if `mv` can not be
+    /// played on this board.
+    pub fn see(&self, mv: &LegalMove) -> Result<i32> {
+        let color = mv.mv.color;
+        let to = mv.mv.to;
+        let mut occupied = self.occupied[0] | self.occupied[1];
+
+        let mut gains = [0; MAX_EXCHANGE];
+        let mut len = 1;
+        match mv.mv.from {
+            Some(from) => {
+                validate_travel(self, &from, &mv.mv)?;
+                let captured = self.piece_type_at(&to, color.opponent());
+                let mut gain = captured.map_or(0, capture_value);
+                if mv.promoted {
+                    gain += piece_value(mv.mv.piece)
+                        - piece_value(mv.mv.piece.revert_promotion().unwrap_or(mv.mv.piece));
+                }
+                gains[0] = gain;
+                occupied.remove(&from);
+            }
+            None => {
+                validate_drop(self, &mv.mv)?;
+                occupied.fill(&to);
+            }
+        }
+
+        let mut on_square = mv.mv.piece;
+        let mut side = color.opponent();
+        while let Some((from, attacker)) = self.least_valuable_attacker(&to, side, occupied) {
+            let mut gain = capture_value(on_square) - gains[len - 1];
+            on_square = attacker;
+            if can_promote(side, &from, &to) {
+                if let Some(promoted) = attacker.promote() {
+                    gain += promotion_value(attacker);
+                    on_square = promoted;
+                }
+            }
+            gains[len] = gain;
+            len += 1;
+            occupied.remove(&from);
+            side = side.opponent();
+        }
+
+        // Each side may stop capturing whenever continuing would lose material.
+        for i in (1..len).rev() {
+            gains[i - 1] = -std::cmp::max(-gains[i - 1], gains[i]);
+        }
+        Ok(gains[0])
+    }
+
+    fn least_valuable_attacker(
+        &self,
+        sq: &Square,
+        color: Color,
+        occupied: Bitboard,
+    ) -> Option<(Square, Piece)> {
+        let attackers = self.attackers_to_occupied(sq, color, occupied);
+        if attackers.is_empty() {
+            return None;
+        }
+        RECAPTURE_ORDER.iter().find_map(|&piece| {
+            (self.piece_bb[piece.to_usize()] & attackers)
+                .squares()
+                .next()
+                .map(|from| (from, piece))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Move, SfenBoard};
+
+    fn see(sfen: &str, from: Option<(u8, u8)>, to: (u8, u8), piece: Piece) -> i32 {
+        let sfen_board = SfenBoard::parse(sfen).unwrap();
+        let color = sfen_board.next_turn;
+        let from = from.map(|(file, rank)| Square::at(file, rank));
+        let mv = LegalMove {
+            mv: Move {
+                color,
+                from,
+                to: Square::at(to.0, to.1),
+                piece,
+            },
+            promoted: piece.is_promoted(),
+        };
+        sfen_board.board.see(&mv).unwrap()
+    }
+
+    #[test]
+    fn test_see() {
+        // An undefended pawn.
+        assert_eq!(
+            see(
+                "9/9/9/4p4/4R4/9/9/9/9 b - 1",
+                Some((5, 5)),
+                (5, 4),
+                Piece::Rook
+            ),
+            180
+        );
+
+        // A pawn defended by a gold is an even trade for a pawn but not for a rook.
+        let sfen = "9/9/9/4g4/4p4/4P4/9/9/9 b - 1";
+        assert_eq!(see(sfen, Some((5, 6)), (5, 5), Piece::Pawn), 0);
+        // With a rook behind the pawn, the gold can no longer recapture.
+        let sfen = "9/9/9/4g4/4p4/4P4/9/4R4/9 b - 1";
+        assert_eq!(see(sfen, Some((5, 6)), (5, 5), Piece::Pawn), 180);
+        let sfen = "9/9/9/4g4/4p4/9/9/4R4/9 b - 1";
+        assert_eq!(see(sfen, Some((5, 8)), (5, 5), Piece::Rook), 180 - 1980);
+
+        // The second rook behind the first one recaptures through the x-ray.
+        let sfen = "9/9/9/4s4/4p4/9/9/4R4/4R4 b - 1";
+        assert_eq!(
+            see(sfen, Some((5, 8)), (5, 5), Piece::Rook),
+            180 - 1980 + 990
+        );
+
+        // Capturing with promotion gains the promotion as well.
+        let sfen = "9/9/4p4/4S4/9/9/9/9/9 b - 1";
+        assert_eq!(
+            see(sfen, Some((5, 4)), (5, 3), Piece::ProSilver),
+            180 + 540 - 495
+        );
+
+        // A gold dropped next to a white silver with nothing behind it is lost.
+        let sfen = "9/9/9/4s4/9/9/9/9/9 b G 1";
+        assert_eq!(see(sfen, None, (5, 5), Piece::Gold), -1080);
+        assert_eq!(see(sfen, None, (5, 7), Piece::Gold), 0);
+    }
+
+    #[test]
+    fn test_see_invalid_move() {
+        let board = SfenBoard::parse("9/9/9/4s4/9/9/9/9/9 b G 1").unwrap().board;
+        let see = |from: Option<Square>, to: Square, piece: Piece| {
+            let mv = LegalMove {
+                mv: Move {
+                    color: Color::Black,
+                    from,
+                    to,
+                    piece,
+                },
+                promoted: false,
+            };
+            board.see(&mv)
+        };
+        // A drop onto the silver, and moves from an empty square and from the white silver.
+        assert!(see(None, Square::at(5, 4), Piece::Gold).is_err());
+        assert!(see(Some(Square::at(5, 5)), Square::at(5, 6), Piece::Gold).is_err());
+        assert!(see(Some(Square::at(5, 4)), Square::at(5, 5), Piece::Silver).is_err());
+    }
+}
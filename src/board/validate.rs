@@ -0,0 +1,195 @@
+use super::legal_move::is_dead_square;
+use crate::{Bitboard, Board, Color, Piece, Square};
+
+/// A reason why a `Board` cannot come from a legal game.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum BoardProblem {
+    /// More pieces of this kind, promoted or not, on the board and in hands than the game has.
+    TooManyPieces {
+        piece: Piece,
+        count: usize,
+    },
+    MissingKing(Color),
+    DuplicateKing(Color),
+    /// The internal bitboards and piece lookup table disagree on what is on the square.
+    /// The public mutators never allow this, so it points to a bug in this crate.
+    OccupiedMismatch(Square),
+    /// The square holds more than one piece or belongs to both colors in the internal
+    /// bitboards. Like `OccupiedMismatch`, this is an internal consistency check.
+    Overlap(Square),
+    /// A piece which can never be in hand, such as a king or a promoted piece. `push_hand`
+    /// refuses these, so this is an internal consistency check as well.
+    InvalidHand {
+        color: Color,
+        piece: Piece,
+    },
+    Nifu {
+        color: Color,
+        file: u8,
+    },
+    /// A piece which can never move again, such as a pawn on the last rank.
+    DeadPiece {
+        color: Color,
+        piece: Piece,
+        square: Square,
+    },
+    /// The side which has just moved left its king in check.
+    OpponentInCheck(Color),
+}
+
+impl Board {
+    /// Returns every problem found in this board when `next_turn` is to move.
+    pub fn validate(&self, next_turn: Color) -> Vec<BoardProblem> {
+        let mut problems = vec![];
+        let occupied = self.occupied[0] | self.occupied[1];
+
+        let mut seen = Bitboard::empty();
+        let mut overlap = self.occupied[0] & self.occupied[1];
+        for &piece_bb in self.piece_bb.iter().skip(1) {
+            overlap = overlap | (seen & piece_bb);
+            seen = seen | piece_bb;
+        }
+        let mut mismatch = (seen ^ occupied) | self.piece_bb[Piece::None.to_usize()];
+        // The mailbox should always agree with the bitboards it is derived from.
+        let expected = self.compute_mailbox();
+        for sq in Square::ALL.iter() {
+            if self.mailbox[sq.index()] != expected[sq.index()] && !overlap.is_filled(sq) {
+                mismatch = mismatch | Bitboard::from(*sq);
+            }
+        }
+        problems.extend(mismatch.squares().map(BoardProblem::OccupiedMismatch));
+        problems.extend(overlap.squares().map(BoardProblem::Overlap));
+
+        for &piece in Piece::HAND.iter() {
+            let mut count = self.piece_bb[piece.to_usize()].0.count_ones() as usize;
+            if let Some(promoted) = piece.promote() {
+                count += self.piece_bb[promoted.to_usize()].0.count_ones() as usize;
+            }
+            for hand in self.pieces_in_hand.iter() {
+                count += hand[piece.to_usize()] as usize;
+                if let Some(promoted) = piece.promote() {
+                    count += hand[promoted.to_usize()] as usize;
+                }
+            }
+            if count > piece.max_piece_in_hand() {
+                problems.push(BoardProblem::TooManyPieces { piece, count });
+            }
+        }
+
+        for &color in [Color::Black, Color::White].iter() {
+            let own = self.occupied[color.to_usize()];
+            match (self.piece_bb[Piece::King.to_usize()] & own).0.count_ones() {
+                0 => problems.push(BoardProblem::MissingKing(color)),
+                1 => {}
+                _ => problems.push(BoardProblem::DuplicateKing(color)),
+            }
+
+            for (piece_id, &count) in self.pieces_in_hand[color.to_usize()].iter().enumerate() {
+                let piece = Piece::ALL[piece_id];
+                if count > 0 && piece.max_piece_in_hand() == 0 {
+                    problems.push(BoardProblem::InvalidHand { color, piece });
+                }
+            }
+
+            let pawns = self.piece_bb[Piece::Pawn.to_usize()] & own;
+            for file in 1..=9 {
+                if pawns.file_count_ones(file) > 1 {
+                    problems.push(BoardProblem::Nifu { color, file });
+                }
+            }
+
+            for &piece in [Piece::Pawn, Piece::Lance, Piece::Knight].iter() {
+                for square in (self.piece_bb[piece.to_usize()] & own).squares() {
+                    if is_dead_square(color, piece, &square) {
+                        problems.push(BoardProblem::DeadPiece {
+                            color,
+                            piece,
+                            square,
+                        });
+                    }
+                }
+            }
+        }
+
+        if self.in_check(next_turn.opponent()) {
+            problems.push(BoardProblem::OpponentInCheck(next_turn.opponent()));
+        }
+        problems
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SfenBoard;
+
+    #[test]
+    fn test_validate() {
+        assert!(Board::default().validate(Color::Black).is_empty());
+        assert!(Board::default().validate(Color::White).is_empty());
+
+        // Black's king is missing, two black pawns share file 5, a white knight sits on the
+        // last rank, and white is in check with black to move.
+        let sfen = "4k4/4P4/9/9/4P4/9/9/9/n8 b 18p 1";
+        let board = SfenBoard::parse(sfen).unwrap().board;
+        let sq = |file, rank| Square::at(file, rank);
+        assert_eq!(
+            board.validate(Color::Black),
+            vec![
+                BoardProblem::TooManyPieces {
+                    piece: Piece::Pawn,
+                    count: 20
+                },
+                BoardProblem::MissingKing(Color::Black),
+                BoardProblem::Nifu {
+                    color: Color::Black,
+                    file: 5
+                },
+                BoardProblem::DeadPiece {
+                    color: Color::White,
+                    piece: Piece::Knight,
+                    square: sq(9, 9)
+                },
+                BoardProblem::OpponentInCheck(Color::White),
+            ]
+        );
+        assert!(!board
+            .validate(Color::White)
+            .contains(&BoardProblem::OpponentInCheck(Color::White)));
+
+        // Corrupt the private fields, which only code in this crate can do.
+        let mut board = Board::default();
+        board.piece_bb[Piece::Gold.to_usize()].fill(&sq(5, 9));
+        board.occupied[1].fill(&sq(5, 5));
+        board.pieces_in_hand[0][Piece::King.to_usize()] = 1;
+        assert_eq!(
+            board.validate(Color::Black),
+            vec![
+                BoardProblem::OccupiedMismatch(sq(5, 5)),
+                BoardProblem::Overlap(sq(5, 9)),
+                BoardProblem::TooManyPieces {
+                    piece: Piece::Gold,
+                    count: 5
+                },
+                BoardProblem::InvalidHand {
+                    color: Color::Black,
+                    piece: Piece::King
+                },
+            ]
+        );
+
+        // Moving a piece in the bitboards without updating the mailbox is caught too.
+        let mut board = Board::default();
+        board.piece_bb[Piece::Pawn.to_usize()].remove(&sq(7, 7));
+        board.piece_bb[Piece::Pawn.to_usize()].fill(&sq(7, 6));
+        board.occupied[0].remove(&sq(7, 7));
+        board.occupied[0].fill(&sq(7, 6));
+        assert_eq!(
+            board.validate(Color::White),
+            vec![
+                BoardProblem::OccupiedMismatch(sq(7, 6)),
+                BoardProblem::OccupiedMismatch(sq(7, 7)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,66 @@
+use super::PIECE_TYPES;
+
+const HAND_PIECE_TYPES: usize = 8;
+const MAX_HAND: usize = 19;
+
+const fn xorshift(mut x: u64) -> u64 {
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    x
+}
+
+const fn piece_keys() -> [[[u64; 81]; PIECE_TYPES]; 2] {
+    let mut keys = [[[0; 81]; PIECE_TYPES]; 2];
+    let mut state = 0x9E37_79B9_7F4A_7C15;
+    let mut color = 0;
+    while color < 2 {
+        let mut piece = 0;
+        while piece < PIECE_TYPES {
+            let mut pos = 0;
+            while pos < 81 {
+                state = xorshift(state);
+                keys[color][piece][pos] = state;
+                pos += 1;
+            }
+            piece += 1;
+        }
+        color += 1;
+    }
+    keys
+}
+
+const fn hand_keys() -> [[[u64; MAX_HAND]; HAND_PIECE_TYPES]; 2] {
+    let mut keys = [[[0; MAX_HAND]; HAND_PIECE_TYPES]; 2];
+    let mut state = 0xD1B5_4A32_D192_ED03;
+    let mut color = 0;
+    while color < 2 {
+        let mut piece = 0;
+        while piece < HAND_PIECE_TYPES {
+            let mut count = 0;
+            while count < MAX_HAND {
+                state = xorshift(state);
+                keys[color][piece][count] = state;
+                count += 1;
+            }
+            piece += 1;
+        }
+        color += 1;
+    }
+    keys
+}
+
+static PIECE_KEYS: [[[u64; 81]; PIECE_TYPES]; 2] = piece_keys();
+static HAND_KEYS: [[[u64; MAX_HAND]; HAND_PIECE_TYPES]; 2] = hand_keys();
+
+/// Toggled on every move, so that the same placement differs by the side to move.
+pub(super) const SIDE_KEY: u64 = 0xA076_1D64_78BD_642F;
+
+pub(super) fn piece_key(color: usize, piece: usize, pos: u8) -> u64 {
+    PIECE_KEYS[color][piece][pos as usize]
+}
+
+/// The key of the `count`-th (0-indexed) `piece` in the hand of `color`.
+pub(super) fn hand_key(color: usize, piece: usize, count: u8) -> u64 {
+    HAND_KEYS[color][piece][count as usize]
+}
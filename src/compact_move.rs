@@ -0,0 +1,204 @@
+use crate::error::ShogiUtilError::InvalidMove;
+use crate::{Board, Color, LegalMove, Move, Piece, Result, ShogiUtilError, Square};
+use std::convert::TryFrom;
+
+const SQUARE_MASK: u16 = 0x7f;
+const FROM_SHIFT: u16 = 7;
+const PROMOTE_BIT: u16 = 1 << 14;
+const COLOR_BIT: u16 = 1 << 15;
+/// Drops are stored in the `from` field as `DROP_OFFSET + piece id`, past the last square.
+const DROP_OFFSET: u16 = 80;
+
+/// A move packed into 16 bits: the destination in bits 0-6, the origin square or the dropped
+/// piece in bits 7-13, the promotion flag in bit 14 and the color in bit 15.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
+pub struct CompactMove(pub u16);
+
+impl CompactMove {
+    /// Encodes `mv`, looking up the moving piece on `board` to tell whether it promotes.
+    pub fn from_move(mv: &Move, board: &Board) -> Result<Self> {
+        let promoted = match mv.from {
+            Some(from) => {
+                let piece = board.piece_type_at(&from, mv.color).ok_or_else(|| {
+                    InvalidMove(format!("{:?} has no piece at {}", mv.color, from))
+                })?;
+                if piece != mv.piece && !piece.is_valid_promotion(&mv.piece) {
+                    return Err(InvalidMove(format!(
+                        "Invalid promotion: {:?} => {:?}",
+                        piece, mv.piece
+                    )));
+                }
+                piece != mv.piece
+            }
+            None => false,
+        };
+        Self::try_from(LegalMove { mv: *mv, promoted })
+    }
+
+    /// Decodes this move, looking up the moving piece on `board`.
+    pub fn to_move(&self, board: &Board) -> Result<Move> {
+        Ok(self.to_legal_move(board)?.mv)
+    }
+
+    pub fn to_legal_move(&self, board: &Board) -> Result<LegalMove> {
+        let e = || InvalidMove(format!("Invalid compact move: {:#06x}", self.0));
+        let color = self.color();
+        let to = self.to_square().ok_or_else(e)?;
+        let promoted = self.is_promotion();
+        let mv = match self.from_square() {
+            Some(from) => {
+                let piece = board.piece_type_at(&from, color).ok_or_else(e)?;
+                let piece = if promoted {
+                    piece.promote().ok_or_else(e)?
+                } else {
+                    piece
+                };
+                Move {
+                    color,
+                    from: Some(from),
+                    to,
+                    piece,
+                }
+            }
+            None => {
+                let piece = self.drop_piece().ok_or_else(e)?;
+                if promoted {
+                    return Err(e());
+                }
+                Move {
+                    color,
+                    from: None,
+                    to,
+                    piece,
+                }
+            }
+        };
+        Ok(LegalMove { mv, promoted })
+    }
+
+    pub fn color(&self) -> Color {
+        if self.0 & COLOR_BIT == 0 {
+            Color::Black
+        } else {
+            Color::White
+        }
+    }
+
+    pub fn to_square(&self) -> Option<Square> {
+        Square::from_index((self.0 & SQUARE_MASK) as u8)
+    }
+
+    /// The origin of the move, or `None` for drops.
+    pub fn from_square(&self) -> Option<Square> {
+        Square::from_index(((self.0 >> FROM_SHIFT) & SQUARE_MASK) as u8)
+    }
+
+    /// The dropped piece, or `None` for moves on the board.
+    pub fn drop_piece(&self) -> Option<Piece> {
+        let from = (self.0 >> FROM_SHIFT) & SQUARE_MASK;
+        match from.checked_sub(DROP_OFFSET) {
+            Some(id @ 1..=7) => Some(Piece::ALL[id as usize]),
+            _ => None,
+        }
+    }
+
+    pub fn is_promotion(&self) -> bool {
+        self.0 & PROMOTE_BIT != 0
+    }
+}
+
+/// Fails if `mv` could not be decoded back, such as a promoted piece or a king being dropped.
+impl TryFrom<LegalMove> for CompactMove {
+    type Error = ShogiUtilError;
+
+    fn try_from(mv: LegalMove) -> Result<Self> {
+        let piece = mv.mv.piece;
+        let from = match mv.mv.from {
+            Some(_) if piece == Piece::None || (mv.promoted && !piece.is_promoted()) => None,
+            Some(from) => Some(from.index() as u16),
+            None if mv.promoted || piece.max_piece_in_hand() == 0 => None,
+            None => Some(DROP_OFFSET + piece.to_byte() as u16),
+        };
+        let from = from.ok_or_else(|| InvalidMove(format!("{:?} can not be encoded", mv)))?;
+        let mut bits = mv.mv.to.index() as u16 | from << FROM_SHIFT;
+        if mv.promoted {
+            bits |= PROMOTE_BIT;
+        }
+        if mv.mv.color == Color::White {
+            bits |= COLOR_BIT;
+        }
+        Ok(CompactMove(bits))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SfenBoard;
+
+    #[test]
+    fn test_round_trip() {
+        let sfen = "ln1g3+Rl/2s1k4/p1pp1p2p/4p1p2/9/2P1P4/PPSP1PP1P/7R1/LN2KGSNL b BG2Pbsn2p 1";
+        let board = SfenBoard::parse(sfen).unwrap().board;
+        for &color in [Color::Black, Color::White].iter() {
+            let moves = board.generate_legal_moves(color);
+            assert!(!moves.is_empty());
+            for mv in moves {
+                let compact = CompactMove::try_from(mv).unwrap();
+                assert_eq!(CompactMove::from_move(&mv.mv, &board).unwrap(), compact);
+                assert_eq!(compact.to_legal_move(&board).unwrap(), mv);
+                assert_eq!(compact.to_move(&board).unwrap(), mv.mv);
+                assert_eq!(compact.color(), color);
+                assert_eq!(compact.from_square(), mv.mv.from);
+                assert_eq!(compact.to_square(), Some(mv.mv.to));
+            }
+        }
+        assert_eq!(std::mem::size_of::<CompactMove>(), 2);
+    }
+
+    #[test]
+    fn test_invalid_encoding() {
+        let board = Board::default();
+        // The destination is past the last square.
+        assert!(CompactMove(81).to_move(&board).is_err());
+        // There is no piece on 5e.
+        let from = Square::at(5, 5).index() as u16;
+        assert!(CompactMove(from << FROM_SHIFT).to_move(&board).is_err());
+        // Kings can not be dropped.
+        let drop = (DROP_OFFSET + Piece::King.to_byte() as u16) << FROM_SHIFT;
+        assert!(CompactMove(drop).to_move(&board).is_err());
+    }
+
+    #[test]
+    fn test_unencodable_moves() {
+        let board = Board::default();
+        let travel = |piece| Move {
+            color: Color::Black,
+            from: Some(Square::at(7, 7)),
+            to: Square::at(7, 6),
+            piece,
+        };
+        let drop = |piece| Move {
+            color: Color::Black,
+            from: None,
+            to: Square::at(5, 5),
+            piece,
+        };
+        assert!(CompactMove::from_move(&travel(Piece::Pawn), &board).is_ok());
+        // A pawn can not turn into a rook.
+        assert!(CompactMove::from_move(&travel(Piece::Rook), &board).is_err());
+        for &piece in [Piece::None, Piece::King, Piece::ProRook].iter() {
+            assert!(CompactMove::from_move(&drop(piece), &board).is_err());
+            let mv = LegalMove {
+                mv: drop(piece),
+                promoted: false,
+            };
+            assert!(CompactMove::try_from(mv).is_err());
+        }
+        let mv = LegalMove {
+            mv: travel(Piece::Pawn),
+            promoted: true,
+        };
+        assert!(CompactMove::try_from(mv).is_err());
+    }
+}
@@ -0,0 +1,137 @@
+use crate::{Board, Color};
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Repetition {
+    None,
+    Draw,
+    PerpetualCheck { loser: Color },
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+struct Entry {
+    hash_key: u64,
+    next_turn: Color,
+    in_check: bool,
+}
+
+/// Positions of a game in the order they appeared, used to detect sennichite.
+#[derive(Debug, Eq, PartialEq, Clone, Default, Hash)]
+pub struct History {
+    entries: Vec<Entry>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, board: &Board, next_turn: Color) {
+        self.entries.push(Entry {
+            hash_key: board.hash_key(),
+            next_turn,
+            in_check: board.in_check(next_turn),
+        });
+    }
+
+    /// Removes the latest position, when its move is taken back.
+    pub fn pop(&mut self) {
+        self.entries.pop();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns how many times the latest position has appeared so far.
+    pub fn count_latest(&self) -> usize {
+        match self.entries.last() {
+            Some(last) => self.occurrences(last).count(),
+            None => 0,
+        }
+    }
+
+    /// Judges the latest position, which is a sennichite if it has appeared four times.
+    pub fn repetition(&self) -> Repetition {
+        let last = match self.entries.last() {
+            Some(last) => last,
+            None => return Repetition::None,
+        };
+        let occurrences = self.occurrences(last).collect::<Vec<_>>();
+        if occurrences.len() < 4 {
+            return Repetition::None;
+        }
+        let first = occurrences[occurrences.len() - 4];
+
+        // Each entry is the position after a move by the opponent of `next_turn`.
+        let checked_every_move = |color: Color| {
+            self.entries[first + 1..]
+                .iter()
+                .filter(|e| e.next_turn == color.opponent())
+                .all(|e| e.in_check)
+        };
+        if checked_every_move(Color::Black) {
+            Repetition::PerpetualCheck {
+                loser: Color::Black,
+            }
+        } else if checked_every_move(Color::White) {
+            Repetition::PerpetualCheck {
+                loser: Color::White,
+            }
+        } else {
+            Repetition::Draw
+        }
+    }
+
+    fn occurrences<'a>(&'a self, target: &'a Entry) -> impl Iterator<Item = usize> + 'a {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter(move |(_, e)| e.next_turn == target.next_turn && e.hash_key == target.hash_key)
+            .map(|(i, _)| i)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Move, Piece, Square};
+
+    fn push_rook_shuffle(board: &mut Board, history: &mut History) {
+        let moves = [
+            (Color::Black, 2, 8, 3, 8, Piece::Rook),
+            (Color::White, 8, 2, 7, 2, Piece::Rook),
+            (Color::Black, 3, 8, 2, 8, Piece::Rook),
+            (Color::White, 7, 2, 8, 2, Piece::Rook),
+        ];
+        for &(color, from_file, from_rank, file, rank, piece) in moves.iter() {
+            board
+                .push_move(Move {
+                    color,
+                    from: Some(Square::at(from_file, from_rank)),
+                    to: Square::at(file, rank),
+                    piece,
+                })
+                .unwrap();
+            history.push(board, color.opponent());
+        }
+    }
+
+    #[test]
+    fn test_fourfold_repetition() {
+        let mut board = Board::default();
+        let mut history = History::new();
+        history.push(&board, Color::Black);
+
+        for i in 1..=3 {
+            assert_eq!(history.repetition(), Repetition::None);
+            push_rook_shuffle(&mut board, &mut history);
+            assert_eq!(history.count_latest(), i + 1);
+        }
+        assert_eq!(history.repetition(), Repetition::Draw);
+        assert_eq!(history.len(), 13);
+    }
+}
@@ -1,15 +1,25 @@
 mod board;
+mod compact_move;
 mod error;
+mod history;
 mod model;
+mod nyugyoku;
 mod parser;
 mod piece;
+mod position;
 mod usi;
 
-pub use board::{Bitboard, Board};
+pub use board::{
+    Bitboard, Board, BoardProblem, Direction, GameStatus, Hand, MoveList, MoveResult, MAX_MOVES,
+};
+pub use compact_move::CompactMove;
 pub use error::{Result, ShogiUtilError};
+pub use history::{History, Repetition};
 pub use model::{Color, LegalMove, Move, Square};
-pub use parser::{parse_csa_string, ParsedCsa};
-pub use piece::Piece;
+pub use nyugyoku::{Declaration, DeclarationRule};
+pub use parser::{parse_csa_string, EndOfGame, ParsedCsa};
+pub use piece::{ColoredPiece, Piece};
+pub use position::Position;
 pub use usi::{SfenBoard, SfenMove, UsiRequest, UsiResponse};
 
 #[cfg(test)]
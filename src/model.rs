@@ -1,9 +1,11 @@
 use crate::error::ShogiUtilError::CsaParseError;
 use crate::piece::Piece;
+use crate::usi::to_sfen_square;
 use crate::{Result, ShogiUtilError};
+use std::fmt;
 use std::str::FromStr;
 
-#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
 pub enum Color {
     Black,
     White,
@@ -40,56 +42,101 @@ impl FromStr for Color {
     }
 }
 
-#[derive(Debug, Eq, PartialEq, Clone)]
-pub struct Square {
-    pub file: u8,
-    pub rank: u8,
+/// A square on the board, stored as its bit index `(rank - 1) * 9 + (9 - file)` in a `Bitboard`.
+#[derive(Eq, PartialEq, Copy, Clone, Hash)]
+pub struct Square(u8);
+
+const fn all_squares() -> [Square; 81] {
+    let mut squares = [Square(0); 81];
+    let mut index = 0;
+    while index < 81 {
+        squares[index] = Square(index as u8);
+        index += 1;
+    }
+    squares
 }
 
 impl Square {
-    pub fn from_pos(i: usize, j: usize) -> Square {
-        assert!(i < 9 && j < 9);
-        Square {
-            rank: (i + 1) as u8,
-            file: (9 - j) as u8,
+    /// Every square in the order of their indices, from 9a to 1i.
+    pub const ALL: [Square; 81] = all_squares();
+
+    /// Returns the square at `file` and `rank`, or `None` if either is outside 1 to 9.
+    pub const fn new(file: u8, rank: u8) -> Option<Square> {
+        if 1 <= file && file <= 9 && 1 <= rank && rank <= 9 {
+            Some(Square((rank - 1) * 9 + 9 - file))
+        } else {
+            None
         }
     }
-    pub fn to_pos(&self) -> (usize, usize) {
-        assert!(self.is_valid());
-        let i = self.rank as usize - 1;
-        let j = 9 - self.file as usize;
-        (i, j)
+
+    /// Same as `new`, but panics if the square is outside the board.
+    pub const fn at(file: u8, rank: u8) -> Square {
+        match Square::new(file, rank) {
+            Some(sq) => sq,
+            None => panic!("square out of the board"),
+        }
     }
 
-    pub fn is_valid(&self) -> bool {
-        1 <= self.rank && self.rank <= 9 && 1 <= self.file && self.file <= 9
+    pub const fn from_index(index: u8) -> Option<Square> {
+        if index < 81 {
+            Some(Square(index))
+        } else {
+            None
+        }
     }
-    pub fn rotate(&self) -> Self {
-        Self {
-            rank: 9 - self.rank + 1,
-            file: 9 - self.file + 1,
+
+    pub const fn index(&self) -> usize {
+        self.0 as usize
+    }
+
+    pub const fn file(&self) -> u8 {
+        9 - self.0 % 9
+    }
+
+    pub const fn rank(&self) -> u8 {
+        self.0 / 9 + 1
+    }
+
+    /// The rank seen from `color`, which is 1 on the far end of the board for both colors.
+    pub const fn relative_rank(&self, color: Color) -> u8 {
+        match color {
+            Color::Black => self.rank(),
+            Color::White => 10 - self.rank(),
         }
     }
+
+    pub const fn rotate(&self) -> Self {
+        Square(80 - self.0)
+    }
+
+    pub const fn mirror(&self) -> Self {
+        Square::at(10 - self.file(), self.rank())
+    }
+}
+
+impl fmt::Debug for Square {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Square({})", self)
+    }
 }
 
 impl FromStr for Square {
     type Err = ShogiUtilError;
 
     fn from_str(s: &str) -> Result<Self> {
-        if s.len() != 2 {
-            Err(CsaParseError(format!("Invalid square: {}", s)))
-        } else {
-            let file = s[0..1].parse::<u8>();
-            let rank = s[1..2].parse::<u8>();
-            match (file, rank) {
-                (Ok(file), Ok(rank)) if file > 0 && rank > 0 => Ok(Square { file, rank }),
-                _ => Err(CsaParseError(format!("Invalid square {}", s))),
-            }
+        let mut chars = s.chars();
+        match (chars.next(), chars.next(), chars.next()) {
+            (Some(file), Some(rank), None) => match (file.to_digit(10), rank.to_digit(10)) {
+                (Some(file), Some(rank)) => Square::new(file as u8, rank as u8),
+                _ => None,
+            },
+            _ => None,
         }
+        .ok_or_else(|| CsaParseError(format!("Invalid square: {}", s)))
     }
 }
 
-#[derive(Debug, Eq, PartialEq, Clone)]
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
 pub struct Move {
     pub color: Color,
     pub from: Option<Square>,
@@ -97,28 +144,117 @@ pub struct Move {
     pub piece: Piece,
 }
 
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
 pub struct LegalMove {
     pub mv: Move,
     pub promoted: bool,
 }
 
+/// Renders the square as in CSA, e.g. `76`.
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.file(), self.rank())
+    }
+}
+
+/// Renders the move in CSA notation, e.g. `+7776FU` or `-0055KA`.
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let color = match self.color {
+            Color::Black => '+',
+            Color::White => '-',
+        };
+        match self.from {
+            Some(from) => write!(f, "{}{}{}{}", color, from, self.to, self.piece.to_csa()),
+            None => write!(f, "{}00{}{}", color, self.to, self.piece.to_csa()),
+        }
+    }
+}
+
+/// Renders the move in USI notation, e.g. `7g7f`, `8h2b+` or `B*5e`.
+impl fmt::Display for LegalMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut s = String::new();
+        match self.mv.from {
+            Some(from) => {
+                to_sfen_square(&from, &mut s);
+                to_sfen_square(&self.mv.to, &mut s);
+                if self.promoted {
+                    s.push('+');
+                }
+            }
+            None => {
+                s.push_str(self.mv.piece.to_sfen());
+                s.push('*');
+                to_sfen_square(&self.mv.to, &mut s);
+            }
+        }
+        write!(f, "{}", s)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     #[test]
     fn test_square() {
-        let sq = Square { file: 2, rank: 3 };
-        assert_eq!(sq.to_pos(), (2, 7));
+        let sq = Square::at(2, 3);
+        assert_eq!((sq.file(), sq.rank()), (2, 3));
+        assert_eq!(sq.index(), 25);
+        assert_eq!(Square::from_index(25), Some(sq));
+        assert_eq!(Square::new(2, 3), Some(sq));
+        assert_eq!(sq.relative_rank(Color::Black), 3);
+        assert_eq!(sq.relative_rank(Color::White), 7);
 
-        let sq = Square::from_pos(2, 7);
-        assert_eq!(Square { file: 2, rank: 3 }, sq);
+        assert_eq!(Square::new(0, 3), None);
+        assert_eq!(Square::new(2, 10), None);
+        assert_eq!(Square::from_index(81), None);
+        assert!(Square::ALL
+            .iter()
+            .enumerate()
+            .all(|(i, sq)| sq.index() == i));
+
+        assert_eq!("23".parse::<Square>().unwrap(), sq);
+        assert!("20".parse::<Square>().is_err());
+        assert!("A9".parse::<Square>().is_err());
+        assert!("234".parse::<Square>().is_err());
+        assert!("é".parse::<Square>().is_err());
+        assert!("2".parse::<Square>().is_err());
     }
 
     #[test]
     fn test_rotate_square() {
-        let sq = Square { file: 2, rank: 7 };
-        let rotated = sq.rotate();
-        assert_eq!(rotated.file, 8);
-        assert_eq!(rotated.rank, 3);
+        let sq = Square::at(2, 7);
+        assert_eq!(sq.rotate(), Square::at(8, 3));
+        assert_eq!(sq.mirror(), Square::at(8, 7));
+    }
+
+    #[test]
+    fn test_display_move() {
+        let sq = Square::at;
+        let travel = LegalMove {
+            mv: Move {
+                color: Color::Black,
+                from: Some(sq(8, 8)),
+                to: sq(2, 2),
+                piece: Piece::ProBishop,
+            },
+            promoted: true,
+        };
+        assert_eq!(travel.to_string(), "8h2b+");
+        assert_eq!(travel.mv.to_string(), "+8822UM");
+
+        let drop = LegalMove {
+            mv: Move {
+                color: Color::White,
+                from: None,
+                to: sq(5, 5),
+                piece: Piece::Pawn,
+            },
+            promoted: false,
+        };
+        assert_eq!(drop.to_string(), "P*5e");
+        assert_eq!(drop.mv.to_string(), "-0055FU");
+        assert_eq!(sq(7, 6).to_string(), "76");
     }
 }
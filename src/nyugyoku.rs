@@ -0,0 +1,195 @@
+use crate::{Bitboard, Board, Color, Piece, Square};
+
+/// Rules to judge a declaration of an entering king (nyugyoku).
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum DeclarationRule {
+    /// The CSA rule: 28 points for Black and 27 points for White win.
+    Csa27,
+    /// The 24-point rule: 31 points or more win and 24 to 30 points draw.
+    Point24,
+    /// The Try rule: the king reaching the initial square of the opponent king wins.
+    Try,
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Declaration {
+    Win,
+    Draw,
+    Invalid,
+}
+
+impl Board {
+    /// Judges a declaration by `color`, who is assumed to be the side to move.
+    pub fn declaration(&self, color: Color, rule: DeclarationRule) -> Declaration {
+        match rule {
+            DeclarationRule::Try => {
+                if self.can_try(color) {
+                    Declaration::Win
+                } else {
+                    Declaration::Invalid
+                }
+            }
+            DeclarationRule::Csa27 => {
+                let required = match color {
+                    Color::Black => 28,
+                    Color::White => 27,
+                };
+                if self.is_entering_king(color) && self.declaration_points(color) >= required {
+                    Declaration::Win
+                } else {
+                    Declaration::Invalid
+                }
+            }
+            DeclarationRule::Point24 => {
+                if !self.is_entering_king(color) {
+                    return Declaration::Invalid;
+                }
+                match self.declaration_points(color) {
+                    points if points >= 31 => Declaration::Win,
+                    points if points >= 24 => Declaration::Draw,
+                    _ => Declaration::Invalid,
+                }
+            }
+        }
+    }
+
+    pub fn can_declare_win(&self, color: Color, rule: DeclarationRule) -> bool {
+        self.declaration(color, rule) == Declaration::Win
+    }
+
+    /// Counts 5 points for each bishop and rook and 1 point for each other piece,
+    /// on the opponent camp or in hand, excluding the king.
+    pub fn declaration_points(&self, color: Color) -> u32 {
+        let camp = self.occupied(color) & opponent_camp(color);
+        let mut points = 0;
+        for &piece in Piece::ALL.iter() {
+            let count =
+                (self.piece_bb(piece) & camp).0.count_ones() + self.hand(color).count(piece) as u32;
+            points += count * piece_point(piece);
+        }
+        points
+    }
+
+    fn is_entering_king(&self, color: Color) -> bool {
+        let king = match self.king_square(color) {
+            Some(king) => king,
+            None => return false,
+        };
+        let camp = opponent_camp(color);
+        if !camp.is_filled(&king) {
+            return false;
+        }
+        let others = (self.occupied(color) & camp).0.count_ones() - 1;
+        others >= 10 && !self.in_check(color)
+    }
+
+    fn can_try(&self, color: Color) -> bool {
+        let try_square = match color {
+            Color::Black => Square::at(5, 1),
+            Color::White => Square::at(5, 9),
+        };
+        self.generate_legal_moves(color)
+            .iter()
+            .any(|m| m.mv.piece == Piece::King && m.mv.to == try_square)
+    }
+}
+
+fn opponent_camp(color: Color) -> Bitboard {
+    let ranks = match color {
+        Color::Black => [1, 2, 3],
+        Color::White => [7, 8, 9],
+    };
+    let mut camp = Bitboard::empty();
+    for &rank in ranks.iter() {
+        for file in 1..=9 {
+            camp.fill(&Square::at(file, rank));
+        }
+    }
+    camp
+}
+
+fn piece_point(piece: Piece) -> u32 {
+    match piece {
+        Piece::None | Piece::King => 0,
+        Piece::Bishop | Piece::Rook | Piece::ProBishop | Piece::ProRook => 5,
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SfenBoard;
+
+    #[test]
+    fn test_csa27() {
+        // Black has 11 pieces around the king in White's camp and 44 points in total.
+        let sfen = "+R+B1GK1+P+P+P/4G4/3+P+P+P+P2/9/9/9/9/9/4k4 b RBG2S2N2L8P 1";
+        let board = SfenBoard::parse(sfen).unwrap().board;
+        assert_eq!(board.declaration_points(Color::Black), 44);
+        assert_eq!(
+            board.declaration(Color::Black, DeclarationRule::Csa27),
+            Declaration::Win
+        );
+        assert_eq!(
+            board.declaration(Color::Black, DeclarationRule::Point24),
+            Declaration::Win
+        );
+
+        // Exactly 10 pieces in the camp are enough.
+        let sfen = "+R+B1GK1+P+P+P/4G4/3+P+P+P3/9/9/9/9/9/4k4 b RBG2S2N2L9P 1";
+        let board = SfenBoard::parse(sfen).unwrap().board;
+        assert_eq!(board.declaration_points(Color::Black), 44);
+        assert_eq!(
+            board.declaration(Color::Black, DeclarationRule::Csa27),
+            Declaration::Win
+        );
+
+        // Only 9 pieces in the camp.
+        let sfen = "+R+B1GK1+P+P+P/4G4/3+P+P4/9/9/9/9/9/4k4 b RBG2S2N2L9P 1";
+        let board = SfenBoard::parse(sfen).unwrap().board;
+        assert_eq!(
+            board.declaration(Color::Black, DeclarationRule::Csa27),
+            Declaration::Invalid
+        );
+    }
+
+    #[test]
+    fn test_point24() {
+        // White has 12 pieces with 20 points in Black's camp and nothing in hand.
+        let sfen = "4K4/9/9/9/9/9/ppppp4/+r+b+p+p+p4/1gs1k4 w - 1";
+        let board = SfenBoard::parse(sfen).unwrap().board;
+        assert_eq!(board.declaration_points(Color::White), 20);
+        assert_eq!(
+            board.declaration(Color::White, DeclarationRule::Point24),
+            Declaration::Invalid
+        );
+
+        let sfen = "4K4/9/9/9/9/9/ppppp4/+r+b+p+p+p4/1gs1k4 w rb 1";
+        let board = SfenBoard::parse(sfen).unwrap().board;
+        assert_eq!(board.declaration_points(Color::White), 30);
+        assert_eq!(
+            board.declaration(Color::White, DeclarationRule::Point24),
+            Declaration::Draw
+        );
+        assert_eq!(
+            board.declaration(Color::White, DeclarationRule::Csa27),
+            Declaration::Win
+        );
+    }
+
+    #[test]
+    fn test_try() {
+        let board = SfenBoard::parse("9/4K4/9/9/9/9/9/9/4k4 b - 1")
+            .unwrap()
+            .board;
+        assert!(board.can_declare_win(Color::Black, DeclarationRule::Try));
+        assert!(!board.can_declare_win(Color::White, DeclarationRule::Try));
+
+        // The try square is covered by the white gold.
+        let board = SfenBoard::parse("3g5/4K4/9/9/9/9/9/9/4k4 b - 1")
+            .unwrap()
+            .board;
+        assert!(!board.can_declare_win(Color::Black, DeclarationRule::Try));
+    }
+}
@@ -1,27 +1,98 @@
 use crate::error::ShogiUtilError::CsaParseError;
 use crate::piece::Piece;
-use crate::{Color, Move, Result, Square};
+use crate::{
+    Board, Color, Declaration, DeclarationRule, GameStatus, History, Move, Repetition, Result,
+    Square,
+};
 use std::str::FromStr;
 
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum EndOfGame {
+    Toryo,
+    TimeUp,
+    IllegalMove,
+    IllegalAction(Color),
+    Sennichite,
+    Kachi,
+}
+
 pub struct ParsedCsa {
     pub names: [Option<String>; 2],
+    /// The position given by the `P` lines, or the standard one if there are none.
+    pub initial_board: Board,
     pub winner: Option<Color>,
     pub moves: Vec<Move>,
+    pub next_turn: Option<Color>,
+    pub end_of_game: Option<EndOfGame>,
+}
+
+impl ParsedCsa {
+    /// Replays the moves from the initial position and returns the resulting board.
+    pub fn replay(&self) -> Result<Board> {
+        let mut board = self.initial_board.clone();
+        for mv in self.moves.iter() {
+            board.push_move(*mv)?;
+        }
+        Ok(board)
+    }
+
+    /// Replays the moves and judges whether the final position is a sennichite,
+    /// so that `%SENNICHITE` records can be verified.
+    pub fn repetition(&self) -> Result<Repetition> {
+        let first_turn = match self.moves.first() {
+            Some(mv) => mv.color,
+            None => ensure_initialized(self.next_turn)?,
+        };
+        let mut board = self.initial_board.clone();
+        let mut history = History::new();
+        history.push(&board, first_turn);
+        for mv in self.moves.iter() {
+            board.push_move(*mv)?;
+            history.push(&board, mv.color.opponent());
+        }
+        Ok(history.repetition())
+    }
+
+    /// Judges the entering-king declaration made by the side to move at the end of the record.
+    pub fn declaration(&self, rule: DeclarationRule) -> Result<Declaration> {
+        let next_turn = ensure_initialized(self.next_turn)?;
+        let board = self.replay()?;
+        Ok(board.declaration(next_turn, rule))
+    }
+
+    /// Returns the status of the final position, regardless of the terminal marker.
+    pub fn game_status(&self) -> Result<GameStatus> {
+        let next_turn = ensure_initialized(self.next_turn)?;
+        let board = self.replay()?;
+        Ok(board.game_status(next_turn))
+    }
 }
 
 pub fn parse_csa_string(csa_str: &str) -> Result<ParsedCsa> {
     let mut names = [None, None];
+    let mut initial_board = None;
     let mut current_turn = None;
     let mut moves = vec![];
     let mut loser = None;
+    let mut end_of_game = None;
     for (line_number, line) in csa_str.split('\n').map(|l| l.trim()).enumerate() {
         if line.is_empty() {
             //ignore
         } else {
             match &line[..1] {
-                "'" | "V" | "$" | "T" | "P" => {
+                "'" | "V" | "$" | "T" => {
                     //ignore
                 }
+                "P" => {
+                    if current_turn.is_some() {
+                        return Err(CsaParseError(format!(
+                            "Position after the starting player {}: {}",
+                            line_number + 1,
+                            line
+                        )));
+                    }
+                    parse_position_line(line, &mut initial_board)?;
+                }
                 "N" => match &line[1..2] {
                     "+" => names[0] = Some(line[2..].to_string()),
                     "-" => names[1] = Some(line[2..].to_string()),
@@ -50,20 +121,27 @@ pub fn parse_csa_string(csa_str: &str) -> Result<ParsedCsa> {
                 }
                 "%" => {
                     // end of game
-                    match line {
-                        "%TORYO" | "%TIME_UP" | "%ILLEGAL_MOVE" => {
-                            loser = Some(ensure_initialized(current_turn)?);
-                        }
-                        "%+ILLEGAL_ACTION" => {
-                            loser = Some(Color::Black);
-                        }
-                        "%-ILLEGAL_ACTION" => {
-                            loser = Some(Color::White);
-                        }
+                    let end = match line {
+                        "%TORYO" => EndOfGame::Toryo,
+                        "%TIME_UP" => EndOfGame::TimeUp,
+                        "%ILLEGAL_MOVE" => EndOfGame::IllegalMove,
+                        "%+ILLEGAL_ACTION" => EndOfGame::IllegalAction(Color::Black),
+                        "%-ILLEGAL_ACTION" => EndOfGame::IllegalAction(Color::White),
+                        "%SENNICHITE" => EndOfGame::Sennichite,
+                        "%KACHI" => EndOfGame::Kachi,
                         _ => {
                             return Err(CsaParseError(format!("{} is not supported.", line)));
                         }
-                    }
+                    };
+                    loser = match end {
+                        EndOfGame::Toryo | EndOfGame::TimeUp | EndOfGame::IllegalMove => {
+                            Some(ensure_initialized(current_turn)?)
+                        }
+                        EndOfGame::IllegalAction(color) => Some(color),
+                        EndOfGame::Sennichite => None,
+                        EndOfGame::Kachi => Some(ensure_initialized(current_turn)?.opponent()),
+                    };
+                    end_of_game = Some(end);
                 }
                 "/" => {
                     return Err(CsaParseError("Separator is not supported.".to_string()));
@@ -83,10 +161,87 @@ pub fn parse_csa_string(csa_str: &str) -> Result<ParsedCsa> {
     Ok(ParsedCsa {
         winner,
         names,
+        initial_board: initial_board.unwrap_or_default(),
         moves,
+        next_turn: current_turn,
+        end_of_game,
     })
 }
 
+/// Applies a `PI`, `P1`-`P9`, `P+` or `P-` line to the position parsed so far.
+fn parse_position_line(line: &str, board: &mut Option<Board>) -> Result<()> {
+    let e = || CsaParseError(format!("Invalid position line: {}", line));
+    if !line.is_ascii() || line.len() < 2 {
+        return Err(e());
+    }
+    match &line[1..2] {
+        "I" => {
+            if board.is_some() {
+                return Err(e());
+            }
+            let mut hirate = Board::default();
+            for (sq, piece) in parse_square_pieces(&line[2..])? {
+                let sq = sq.ok_or_else(e)?;
+                match hirate.piece_at(&sq) {
+                    Some((color, p)) if p == piece => {
+                        hirate.remove_piece(&sq, color)?;
+                    }
+                    _ => return Err(e()),
+                }
+            }
+            *board = Some(hirate);
+        }
+        "+" | "-" => {
+            let color = Color::from_str(&line[1..2])?;
+            let board = board.get_or_insert_with(Board::empty);
+            for (sq, piece) in parse_square_pieces(&line[2..])? {
+                match sq {
+                    Some(sq) => board.push_piece(&sq, color, piece),
+                    None if !piece.is_promoted() => board.push_hand(piece, color),
+                    None => return Err(e()),
+                }
+                .map_err(|_| e())?;
+            }
+        }
+        rank => {
+            let rank = rank.parse::<u8>().map_err(|_| e())?;
+            let board = board.get_or_insert_with(Board::empty);
+            // Trailing empty squares lose their last space when the line is trimmed.
+            let row = format!("{:<27}", &line[2..]);
+            if rank == 0 || row.len() != 27 {
+                return Err(e());
+            }
+            for (i, file) in (1..=9).rev().enumerate() {
+                let cell = &row[i * 3..i * 3 + 3];
+                if cell == " * " {
+                    continue;
+                }
+                let color = Color::from_str(&cell[..1])?;
+                let piece = cell[1..].parse::<Piece>()?;
+                board
+                    .push_piece(&Square::at(file, rank), color, piece)
+                    .map_err(|_| e())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parses a sequence of `00FU`-style entries, where `00` stands for the hand.
+fn parse_square_pieces(s: &str) -> Result<Vec<(Option<Square>, Piece)>> {
+    s.as_bytes()
+        .chunks(4)
+        .map(|entry| match std::str::from_utf8(entry) {
+            Ok(entry) if entry.len() == 4 => {
+                let sq = parse_square(&entry[..2])?;
+                let piece = entry[2..].parse::<Piece>()?;
+                Ok((sq, piece))
+            }
+            _ => Err(CsaParseError(format!("Invalid pieces: {}", s))),
+        })
+        .collect()
+}
+
 fn ensure_initialized(color: Option<Color>) -> Result<Color> {
     color.ok_or_else(|| CsaParseError("Starting player is not defined".to_string()))
 }
@@ -118,7 +273,6 @@ fn parse_square(square_str: &str) -> Result<Option<Square>> {
 mod tests {
     use super::*;
     use crate::debug::dump_board;
-    use crate::Board;
 
     #[test]
     fn test_parse_csa() {
@@ -183,10 +337,12 @@ mod tests {
         %TORYO
         ";
         let result = parse_csa_string(csa_str).unwrap();
-        let mut board = Board::default();
-        for mv in result.moves {
-            board.push_move(mv).unwrap();
-        }
+        assert_eq!(result.initial_board, Board::default());
+        assert_eq!(result.winner, Some(Color::Black));
+        assert_eq!(result.end_of_game, Some(EndOfGame::Toryo));
+        assert_eq!(result.next_turn, Some(Color::White));
+        assert_eq!(result.game_status().unwrap(), GameStatus::Checkmated);
+        let board = result.replay().unwrap();
 
         let dumped_board = dump_board(&board);
         assert_eq!(
@@ -207,15 +363,68 @@ P-00FU00FU00FU00GI
     }
 
     #[test]
-    fn test_parse_square() {
+    fn test_parse_sennichite() {
+        let mut csa_str = "V2\nPI\n+\n".to_string();
+        for _ in 0..3 {
+            csa_str += "+2838HI\n-8272HI\n+3828HI\n-7282HI\n";
+        }
+        csa_str += "%SENNICHITE\n";
+        let result = parse_csa_string(&csa_str).unwrap();
+        assert_eq!(result.winner, None);
+        assert_eq!(result.end_of_game, Some(EndOfGame::Sennichite));
+        assert_eq!(result.repetition().unwrap(), Repetition::Draw);
+    }
+
+    #[test]
+    fn test_parse_kachi() {
+        let csa_str = "V2\nPI\n+\n+7776FU\n-3334FU\n%KACHI\n";
+        let result = parse_csa_string(csa_str).unwrap();
+        assert_eq!(result.winner, Some(Color::Black));
+        assert_eq!(result.end_of_game, Some(EndOfGame::Kachi));
         assert_eq!(
-            Some(Square { file: 9, rank: 1 }),
-            parse_square("91").unwrap()
+            result.declaration(DeclarationRule::Csa27).unwrap(),
+            Declaration::Invalid
         );
+    }
+
+    #[test]
+    fn test_parse_initial_position() {
+        // Two-piece handicap: White has neither the rook nor the bishop.
+        let csa_str = "V2\nPI82HI22KA\n-\n-5142OU\n+7776FU\n";
+        let result = parse_csa_string(csa_str).unwrap();
+        let board = result.replay().unwrap();
+        assert_eq!(board.piece_at(&Square::at(8, 2)), None);
+        assert_eq!(board.piece_at(&Square::at(2, 2)), None);
         assert_eq!(
-            Some(Square { file: 1, rank: 1 }),
-            parse_square("11").unwrap()
+            board.piece_at(&Square::at(4, 2)),
+            Some((Color::White, Piece::King))
         );
+        assert_eq!(result.game_status().unwrap(), GameStatus::Ongoing);
+
+        // A tsume position given square by square, with a gold in Black's hand.
+        let csa_str =
+            "P1 *  *  *  * -OU *  *  *  * \nP3 *  *  *  * +FU *  *  *  * \nP+00KI\nP-00AL\n+\n";
+        assert!(parse_csa_string(csa_str).is_err());
+        let csa_str =
+            "P1 *  *  *  * -OU *  *  *  * \nP3 *  *  *  * +FU *  *  *  * \nP+00KI\n+\n+0052KI\n";
+        let result = parse_csa_string(csa_str).unwrap();
+        assert_eq!(
+            result.initial_board.hand(Color::Black).count(Piece::Gold),
+            1
+        );
+        assert_eq!(result.initial_board.pieces().count(), 2);
+        assert_eq!(result.game_status().unwrap(), GameStatus::Checkmated);
+
+        assert!(parse_csa_string("PI55FU\n+\n").is_err());
+        assert!(parse_csa_string("PI\nP+51KI\n+\n").is_err());
+        assert!(parse_csa_string("P0\n+\n").is_err());
+        assert!(parse_csa_string("+\nPI\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_square() {
+        assert_eq!(Some(Square::at(9, 1)), parse_square("91").unwrap());
+        assert_eq!(Some(Square::at(1, 1)), parse_square("11").unwrap());
         assert_eq!(None, parse_square("00").unwrap());
         assert!(parse_square("A1").is_err());
         assert!(parse_square("01").is_err());
@@ -0,0 +1,122 @@
+use crate::board::PIECE_TYPES;
+
+/// Directions as `(d_rank, d_file)`, seen from Black.
+pub(crate) const DIRECTIONS: [(i8, i8); 8] = [
+    (-1, 0),
+    (1, 0),
+    (0, -1),
+    (0, 1),
+    (-1, -1),
+    (-1, 1),
+    (1, -1),
+    (1, 1),
+];
+
+pub(super) const UP: usize = 0;
+pub(super) const DOWN: usize = 1;
+pub(super) const ROOK_DIRECTIONS: [usize; 4] = [0, 1, 2, 3];
+pub(super) const BISHOP_DIRECTIONS: [usize; 4] = [4, 5, 6, 7];
+
+const PAWN: &[(i8, i8)] = &[(-1, 0)];
+const KNIGHT: &[(i8, i8)] = &[(-2, -1), (-2, 1)];
+const SILVER: &[(i8, i8)] = &[(-1, -1), (-1, 0), (-1, 1), (1, -1), (1, 1)];
+const GOLD: &[(i8, i8)] = &[(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, 0)];
+const ORTHOGONAL: &[(i8, i8)] = &[(-1, 0), (1, 0), (0, -1), (0, 1)];
+const DIAGONAL: &[(i8, i8)] = &[(-1, -1), (-1, 1), (1, -1), (1, 1)];
+const NONE: &[(i8, i8)] = &[];
+
+const fn on_board(rank: i8, file: i8) -> bool {
+    1 <= rank && rank <= 9 && 1 <= file && file <= 9
+}
+
+const fn bit(rank: i8, file: i8) -> u128 {
+    1 << ((rank - 1) * 9 + 9 - file)
+}
+
+pub(crate) const fn rays() -> [[u128; 81]; 8] {
+    let mut rays = [[0; 81]; 8];
+    let mut dir = 0;
+    while dir < 8 {
+        let mut pos = 0;
+        while pos < 81 {
+            let (d_rank, d_file) = DIRECTIONS[dir];
+            let mut rank = (pos / 9) as i8 + 1 + d_rank;
+            let mut file = 9 - (pos % 9) as i8 + d_file;
+            while on_board(rank, file) {
+                rays[dir][pos] |= bit(rank, file);
+                rank += d_rank;
+                file += d_file;
+            }
+            pos += 1;
+        }
+        dir += 1;
+    }
+    rays
+}
+
+/// The step moves of each piece, excluding the sliding part of lances, bishops and rooks.
+const fn step_deltas(piece: usize) -> &'static [(i8, i8)] {
+    match piece {
+        1 => PAWN,
+        3 => KNIGHT,
+        4 => SILVER,
+        5 | 9 | 10 | 11 | 12 => GOLD,
+        8 => &DIRECTIONS,
+        13 => ORTHOGONAL,
+        14 => DIAGONAL,
+        _ => NONE,
+    }
+}
+
+const fn step_attacks() -> [[[u128; 81]; PIECE_TYPES]; 2] {
+    let mut attacks = [[[0; 81]; PIECE_TYPES]; 2];
+    let mut color = 0;
+    while color < 2 {
+        let sign = if color == 0 { 1 } else { -1 };
+        let mut piece = 0;
+        while piece < PIECE_TYPES {
+            let deltas = step_deltas(piece);
+            let mut pos = 0;
+            while pos < 81 {
+                let rank = (pos / 9) as i8 + 1;
+                let file = 9 - (pos % 9) as i8;
+                let mut i = 0;
+                while i < deltas.len() {
+                    let (d_rank, d_file) = deltas[i];
+                    let (rank, file) = (rank + d_rank * sign, file + d_file);
+                    if on_board(rank, file) {
+                        attacks[color][piece][pos] |= bit(rank, file);
+                    }
+                    i += 1;
+                }
+                pos += 1;
+            }
+            piece += 1;
+        }
+        color += 1;
+    }
+    attacks
+}
+
+static RAYS: [[u128; 81]; 8] = rays();
+static STEP_ATTACKS: [[[u128; 81]; PIECE_TYPES]; 2] = step_attacks();
+
+pub(super) fn step_attack(color: usize, piece: usize, pos: usize) -> u128 {
+    STEP_ATTACKS[color][piece][pos]
+}
+
+/// Squares reached from `pos` towards `dir`, up to and including the first occupied square.
+pub(super) fn ray_attack(dir: usize, pos: usize, occupied: u128) -> u128 {
+    let ray = RAYS[dir][pos];
+    let blockers = ray & occupied;
+    if blockers == 0 {
+        return ray;
+    }
+    let (d_rank, d_file) = DIRECTIONS[dir];
+    let first = if d_rank > 0 || (d_rank == 0 && d_file < 0) {
+        blockers.trailing_zeros()
+    } else {
+        127 - blockers.leading_zeros()
+    };
+    ray ^ RAYS[dir][first as usize]
+}
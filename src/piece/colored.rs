@@ -0,0 +1,159 @@
+use super::Piece;
+use crate::error::ShogiUtilError::CsaParseError;
+use crate::{Color, ShogiUtilError};
+use std::fmt;
+use std::str::FromStr;
+
+/// A piece on the board or in hand together with its owner. It is never `Piece::None`.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
+pub struct ColoredPiece {
+    color: Color,
+    piece: Piece,
+}
+
+impl ColoredPiece {
+    /// Returns `None` for `Piece::None`.
+    pub fn new(color: Color, piece: Piece) -> Option<Self> {
+        if piece == Piece::None {
+            None
+        } else {
+            Some(Self { color, piece })
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    pub fn piece(&self) -> Piece {
+        self.piece
+    }
+
+    /// Returns the CSA notation, e.g. `+FU` or `-TO`.
+    pub fn to_csa(&self) -> String {
+        let color = match self.color {
+            Color::Black => '+',
+            Color::White => '-',
+        };
+        format!("{}{}", color, self.piece.to_csa())
+    }
+
+    /// Returns the SFEN notation, e.g. `P` for Black or `+p` for White.
+    pub fn to_sfen(&self) -> String {
+        match self.color {
+            Color::Black => self.piece.to_sfen().to_string(),
+            Color::White => self.piece.to_sfen().to_ascii_lowercase(),
+        }
+    }
+
+    pub fn from_sfen(s: &str) -> Option<Self> {
+        let mut chars = s.chars();
+        let (promoted, c) = match (chars.next()?, chars.next(), chars.next()) {
+            ('+', Some(c), None) => (true, c),
+            (c, None, None) => (false, c),
+            _ => return None,
+        };
+        let color = if c.is_ascii_uppercase() {
+            Color::Black
+        } else {
+            Color::White
+        };
+        let piece = match c.to_ascii_uppercase() {
+            'K' => Piece::King,
+            'R' => Piece::Rook,
+            'B' => Piece::Bishop,
+            'G' => Piece::Gold,
+            'S' => Piece::Silver,
+            'N' => Piece::Knight,
+            'L' => Piece::Lance,
+            'P' => Piece::Pawn,
+            _ => return None,
+        };
+        let piece = if promoted { piece.promote()? } else { piece };
+        Some(Self { color, piece })
+    }
+
+    /// Returns the kanji name with the owner's mark, e.g. `▲歩` or `△成香`.
+    pub fn to_kanji(&self) -> String {
+        let color = match self.color {
+            Color::Black => '▲',
+            Color::White => '△',
+        };
+        format!("{}{}", color, self.piece.to_kanji())
+    }
+
+    pub fn from_kanji(s: &str) -> Option<Self> {
+        let mut chars = s.chars();
+        let color = match chars.next()? {
+            '▲' | '☗' => Color::Black,
+            '△' | '☖' => Color::White,
+            _ => return None,
+        };
+        let piece = Piece::from_kanji(chars.as_str())?;
+        Some(Self { color, piece })
+    }
+
+    /// Returns the English name, e.g. `Black Pawn` or `White Dragon`.
+    pub fn english_name(&self) -> String {
+        format!("{:?} {}", self.color, self.piece.english_name())
+    }
+}
+
+/// Parses the CSA notation, e.g. `+FU`.
+impl FromStr for ColoredPiece {
+    type Err = ShogiUtilError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let e = || CsaParseError(format!("Invalid piece: {}", s));
+        if s.len() != 3 || !s.is_char_boundary(1) {
+            return Err(e());
+        }
+        let color = Color::from_str(&s[0..1])?;
+        let piece = Piece::from_str(&s[1..])?;
+        Self::new(color, piece).ok_or_else(e)
+    }
+}
+
+/// Renders the CSA notation, e.g. `+FU`.
+impl fmt::Display for ColoredPiece {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_csa())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions() {
+        for &piece in Piece::ALL.iter().skip(1) {
+            for &color in [Color::Black, Color::White].iter() {
+                let colored = ColoredPiece::new(color, piece).unwrap();
+                assert_eq!(colored.to_csa().parse::<ColoredPiece>().unwrap(), colored);
+                assert_eq!(ColoredPiece::from_sfen(&colored.to_sfen()), Some(colored));
+                assert_eq!(ColoredPiece::from_kanji(&colored.to_kanji()), Some(colored));
+            }
+        }
+        assert_eq!(ColoredPiece::new(Color::Black, Piece::None), None);
+
+        let tokin = ColoredPiece::new(Color::White, Piece::ProPawn).unwrap();
+        assert_eq!(tokin.to_csa(), "-TO");
+        assert_eq!(tokin.to_sfen(), "+p");
+        assert_eq!(tokin.to_kanji(), "△と");
+        assert_eq!(tokin.english_name(), "White Tokin");
+
+        let lance = ColoredPiece::new(Color::Black, Piece::ProLance).unwrap();
+        assert_eq!(lance.to_kanji(), "▲成香");
+        assert_eq!(ColoredPiece::from_kanji("☗杏"), Some(lance));
+        assert_eq!(ColoredPiece::from_sfen("+L"), Some(lance));
+
+        assert!("+* ".parse::<ColoredPiece>().is_err());
+        assert!("FU".parse::<ColoredPiece>().is_err());
+        assert!("+歩".parse::<ColoredPiece>().is_err());
+        assert_eq!(ColoredPiece::from_sfen("+k"), None);
+        assert_eq!(ColoredPiece::from_sfen("+"), None);
+        assert_eq!(ColoredPiece::from_sfen("PP"), None);
+        assert_eq!(ColoredPiece::from_kanji("歩"), None);
+    }
+}
@@ -1,11 +1,16 @@
 use crate::error::ShogiUtilError::CsaParseError;
-use crate::{Bitboard, ShogiUtilError, Square};
+use crate::{Bitboard, Color, ShogiUtilError, Square};
+use std::convert::TryFrom;
 use std::str::FromStr;
 
+pub(crate) mod attacks;
+mod colored;
 mod moves;
+use attacks::{ray_attack, step_attack, BISHOP_DIRECTIONS, DOWN, ROOK_DIRECTIONS, UP};
+pub use colored::ColoredPiece;
 use moves::piece_moves;
 
-#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
 pub enum Piece {
     None,
     Pawn,
@@ -49,30 +54,49 @@ impl FromStr for Piece {
     }
 }
 
-impl From<u8> for Piece {
-    fn from(b: u8) -> Self {
-        match b {
-            0 => Piece::None,
-            1 => Piece::Pawn,
-            2 => Piece::Lance,
-            3 => Piece::Knight,
-            4 => Piece::Silver,
-            5 => Piece::Gold,
-            6 => Piece::Bishop,
-            7 => Piece::Rook,
-            8 => Piece::King,
-            9 => Piece::ProPawn,
-            10 => Piece::ProLance,
-            11 => Piece::ProKnight,
-            12 => Piece::ProSilver,
-            13 => Piece::ProBishop,
-            14 => Piece::ProRook,
-            _ => unreachable!(),
-        }
+impl TryFrom<u8> for Piece {
+    type Error = ShogiUtilError;
+
+    fn try_from(b: u8) -> Result<Self, Self::Error> {
+        Piece::from_byte(b).ok_or_else(|| CsaParseError(format!("Invalid piece byte: {}", b)))
     }
 }
 
 impl Piece {
+    /// Every piece in the order of `to_byte`, which is also the order of `Board::piece_bb`.
+    pub const ALL: [Piece; 15] = [
+        Piece::None,
+        Piece::Pawn,
+        Piece::Lance,
+        Piece::Knight,
+        Piece::Silver,
+        Piece::Gold,
+        Piece::Bishop,
+        Piece::Rook,
+        Piece::King,
+        Piece::ProPawn,
+        Piece::ProLance,
+        Piece::ProKnight,
+        Piece::ProSilver,
+        Piece::ProBishop,
+        Piece::ProRook,
+    ];
+
+    /// Pieces which can be held in hand, in the order of `to_byte`.
+    pub const HAND: [Piece; 7] = [
+        Piece::Pawn,
+        Piece::Lance,
+        Piece::Knight,
+        Piece::Silver,
+        Piece::Gold,
+        Piece::Bishop,
+        Piece::Rook,
+    ];
+
+    pub fn from_byte(b: u8) -> Option<Piece> {
+        Piece::ALL.get(b as usize).copied()
+    }
+
     pub fn to_byte(&self) -> u8 {
         match self {
             Piece::None => 0,
@@ -129,17 +153,88 @@ impl Piece {
         }
     }
 
-    pub fn to_sfen(&self) -> char {
+    /// Returns the SFEN letter of this piece for Black, e.g. `P` or `+P`, and an empty string
+    /// for `Piece::None`.
+    pub fn to_sfen(&self) -> &'static str {
         match self {
-            Piece::Pawn => 'P',
-            Piece::Lance => 'L',
-            Piece::Knight => 'N',
-            Piece::Silver => 'S',
-            Piece::Gold => 'G',
-            Piece::Bishop => 'B',
-            Piece::Rook => 'R',
-            Piece::King => 'K',
-            _ => unreachable!(),
+            Piece::None => "",
+            Piece::Pawn => "P",
+            Piece::Lance => "L",
+            Piece::Knight => "N",
+            Piece::Silver => "S",
+            Piece::Gold => "G",
+            Piece::Bishop => "B",
+            Piece::Rook => "R",
+            Piece::King => "K",
+            Piece::ProPawn => "+P",
+            Piece::ProLance => "+L",
+            Piece::ProKnight => "+N",
+            Piece::ProSilver => "+S",
+            Piece::ProBishop => "+B",
+            Piece::ProRook => "+R",
+        }
+    }
+
+    /// Returns the name in kanji, using the two-letter names for promoted lances, knights and
+    /// silvers.
+    pub fn to_kanji(&self) -> &'static str {
+        match self {
+            Piece::None => "",
+            Piece::Pawn => "歩",
+            Piece::Lance => "香",
+            Piece::Knight => "桂",
+            Piece::Silver => "銀",
+            Piece::Gold => "金",
+            Piece::Bishop => "角",
+            Piece::Rook => "飛",
+            Piece::King => "玉",
+            Piece::ProPawn => "と",
+            Piece::ProLance => "成香",
+            Piece::ProKnight => "成桂",
+            Piece::ProSilver => "成銀",
+            Piece::ProBishop => "馬",
+            Piece::ProRook => "龍",
+        }
+    }
+
+    /// Parses a kanji name, accepting the common variants such as `王`, `竜` and `杏`.
+    pub fn from_kanji(s: &str) -> Option<Piece> {
+        match s {
+            "歩" => Some(Piece::Pawn),
+            "香" => Some(Piece::Lance),
+            "桂" => Some(Piece::Knight),
+            "銀" => Some(Piece::Silver),
+            "金" => Some(Piece::Gold),
+            "角" => Some(Piece::Bishop),
+            "飛" => Some(Piece::Rook),
+            "玉" | "王" => Some(Piece::King),
+            "と" => Some(Piece::ProPawn),
+            "成香" | "杏" => Some(Piece::ProLance),
+            "成桂" | "圭" => Some(Piece::ProKnight),
+            "成銀" | "全" => Some(Piece::ProSilver),
+            "馬" => Some(Piece::ProBishop),
+            "龍" | "竜" => Some(Piece::ProRook),
+            _ => None,
+        }
+    }
+
+    pub fn english_name(&self) -> &'static str {
+        match self {
+            Piece::None => "None",
+            Piece::Pawn => "Pawn",
+            Piece::Lance => "Lance",
+            Piece::Knight => "Knight",
+            Piece::Silver => "Silver",
+            Piece::Gold => "Gold",
+            Piece::Bishop => "Bishop",
+            Piece::Rook => "Rook",
+            Piece::King => "King",
+            Piece::ProPawn => "Tokin",
+            Piece::ProLance => "Promoted Lance",
+            Piece::ProKnight => "Promoted Knight",
+            Piece::ProSilver => "Promoted Silver",
+            Piece::ProBishop => "Horse",
+            Piece::ProRook => "Dragon",
         }
     }
 
@@ -185,8 +280,44 @@ impl Piece {
         }
     }
 
-    pub fn generate_moves(&self, from: &Square, occupied: &[Bitboard; 2], moves: &mut Vec<Square>) {
-        piece_moves(*self, from, occupied, moves)
+    /// Pushes the squares this piece of `color` can reach from `from`.
+    /// `occupied[0]` holds the pieces of `color` and `occupied[1]` those of the opponent.
+    pub fn generate_moves(
+        &self,
+        color: Color,
+        from: &Square,
+        occupied: &[Bitboard; 2],
+        moves: &mut Vec<Square>,
+    ) {
+        piece_moves(*self, color, from, occupied, moves)
+    }
+
+    /// Returns the squares attacked by this piece of `color` on `sq`, where sliders stop at
+    /// the first square in `occupied`, which is included.
+    pub fn attacks(&self, color: Color, sq: &Square, occupied: Bitboard) -> Bitboard {
+        let pos = sq.index();
+        let mut attacks = step_attack(color.to_usize(), self.to_usize(), pos);
+        match self {
+            Piece::Lance => {
+                let dir = match color {
+                    Color::Black => UP,
+                    Color::White => DOWN,
+                };
+                attacks |= ray_attack(dir, pos, occupied.0);
+            }
+            Piece::Bishop | Piece::ProBishop => {
+                for &dir in BISHOP_DIRECTIONS.iter() {
+                    attacks |= ray_attack(dir, pos, occupied.0);
+                }
+            }
+            Piece::Rook | Piece::ProRook => {
+                for &dir in ROOK_DIRECTIONS.iter() {
+                    attacks |= ray_attack(dir, pos, occupied.0);
+                }
+            }
+            _ => {}
+        }
+        Bitboard(attacks)
     }
 }
 
@@ -194,13 +325,55 @@ impl Piece {
 mod tests {
     use crate::debug::generate_bitboard;
     use crate::piece::Piece;
-    use crate::Square;
+    use crate::{Bitboard, Color, Square};
+    use std::convert::TryFrom;
 
     #[test]
     fn test_from_to_byte() {
         for i in 0..15 {
-            let piece = Piece::from(i);
+            let piece = Piece::from_byte(i).unwrap();
             assert_eq!(piece.to_byte(), i);
+            assert_eq!(Piece::ALL[i as usize], piece);
+        }
+        assert_eq!(Piece::from_byte(15), None);
+        assert_eq!(Piece::try_from(9).unwrap(), Piece::ProPawn);
+        assert!(Piece::try_from(15).is_err());
+    }
+
+    #[test]
+    fn test_sfen() {
+        assert_eq!(Piece::Pawn.to_sfen(), "P");
+        assert_eq!(Piece::ProPawn.to_sfen(), "+P");
+        assert_eq!(Piece::None.to_sfen(), "");
+    }
+
+    #[test]
+    fn test_attacks_match_generate_moves() {
+        let mut state: u128 = 0x2545_F491_4F6C_DD1D;
+        for _ in 0..20 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let occupied = Bitboard(state & (state >> 3) & Bitboard::full().0);
+            for &piece in Piece::ALL.iter() {
+                for &color in [Color::Black, Color::White].iter() {
+                    for file in 1..=9 {
+                        for rank in 1..=9 {
+                            let sq = Square::at(file, rank);
+                            let mut moves = vec![];
+                            piece.generate_moves(
+                                color,
+                                &sq,
+                                &[Bitboard::empty(), occupied],
+                                &mut moves,
+                            );
+                            let attacks = piece.attacks(color, &sq, occupied);
+                            assert_eq!(attacks.0.count_ones() as usize, moves.len());
+                            assert!(moves.iter().all(|to| attacks.is_filled(to)));
+                        }
+                    }
+                }
+            }
         }
     }
 
@@ -235,7 +408,7 @@ mod tests {
             ),
         ];
         let mut moves = vec![];
-        Piece::Pawn.generate_moves(&Square { file: 5, rank: 5 }, &occupied, &mut moves);
+        Piece::Pawn.generate_moves(Color::Black, &Square::at(5, 5), &occupied, &mut moves);
         assert!(moves.is_empty());
 
         let occupied = [
@@ -267,8 +440,8 @@ mod tests {
             ),
         ];
         let mut moves = vec![];
-        Piece::Pawn.generate_moves(&Square { file: 5, rank: 5 }, &occupied, &mut moves);
-        assert_eq!(moves, [Square { file: 5, rank: 4 }]);
+        Piece::Pawn.generate_moves(Color::Black, &Square::at(5, 5), &occupied, &mut moves);
+        assert_eq!(moves, [Square::at(5, 4)]);
     }
     #[test]
     fn test_lance_generate_moves() {
@@ -301,11 +474,8 @@ mod tests {
             ),
         ];
         let mut moves = vec![];
-        Piece::Lance.generate_moves(&Square { file: 5, rank: 5 }, &occupied, &mut moves);
-        assert_eq!(
-            moves,
-            [Square { file: 5, rank: 4 }, Square { file: 5, rank: 3 }]
-        );
+        Piece::Lance.generate_moves(Color::Black, &Square::at(5, 5), &occupied, &mut moves);
+        assert_eq!(moves, [Square::at(5, 4), Square::at(5, 3)]);
 
         let occupied = [
             generate_bitboard(
@@ -336,14 +506,10 @@ mod tests {
             ),
         ];
         let mut moves = vec![];
-        Piece::Lance.generate_moves(&Square { file: 5, rank: 5 }, &occupied, &mut moves);
+        Piece::Lance.generate_moves(Color::Black, &Square::at(5, 5), &occupied, &mut moves);
         assert_eq!(
             moves,
-            [
-                Square { file: 5, rank: 4 },
-                Square { file: 5, rank: 3 },
-                Square { file: 5, rank: 2 }
-            ]
+            [Square::at(5, 4), Square::at(5, 3), Square::at(5, 2)]
         );
     }
 }
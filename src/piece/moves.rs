@@ -1,6 +1,7 @@
-use crate::{Bitboard, Piece, Square};
+use crate::{Bitboard, Color, Piece, Square};
 
 fn generate_moves(
+    color: Color,
     from: &Square,
     occupied: &[Bitboard],
     result: &mut Vec<Square>,
@@ -8,13 +9,14 @@ fn generate_moves(
     d_file: i8,
     one: bool,
 ) {
-    let mut next_rank = from.rank as i8 + d_rank;
-    let mut next_file = from.file as i8 + d_file;
-    while 1 <= next_rank && next_rank <= 9 && 1 <= next_file && next_file <= 9 {
-        let next = Square {
-            rank: next_rank as u8,
-            file: next_file as u8,
-        };
+    // Directions are written from Black's point of view; White moves down the board.
+    let d_rank = match color {
+        Color::Black => d_rank,
+        Color::White => -d_rank,
+    };
+    let mut next_rank = from.rank() as i8 + d_rank;
+    let mut next_file = from.file() as i8 + d_file;
+    while let Some(next) = Square::new(next_file as u8, next_rank as u8) {
         if occupied[0].is_filled(&next) {
             break;
         }
@@ -34,63 +36,65 @@ const BISHOP_DIR: [(i8, i8); 4] = [(1, 1), (-1, 1), (1, -1), (-1, -1)];
 
 pub(super) fn piece_moves(
     piece: Piece,
+    color: Color,
     from: &Square,
     occupied: &[Bitboard],
     result: &mut Vec<Square>,
 ) {
     use Piece::*;
+    let c = color;
     match piece {
-        Pawn => generate_moves(from, occupied, result, -1, 0, true),
-        Lance => generate_moves(from, occupied, result, -1, 0, false),
+        Pawn => generate_moves(c, from, occupied, result, -1, 0, true),
+        Lance => generate_moves(c, from, occupied, result, -1, 0, false),
         Knight => {
-            generate_moves(from, occupied, result, -2, -1, true);
-            generate_moves(from, occupied, result, -2, 1, true);
+            generate_moves(c, from, occupied, result, -2, -1, true);
+            generate_moves(c, from, occupied, result, -2, 1, true);
         }
         Silver => {
             for &(d_rank, d_file) in BISHOP_DIR.iter() {
-                generate_moves(from, occupied, result, d_rank, d_file, true);
+                generate_moves(c, from, occupied, result, d_rank, d_file, true);
             }
-            generate_moves(from, occupied, result, -1, 0, true);
+            generate_moves(c, from, occupied, result, -1, 0, true);
         }
         Gold | ProPawn | ProLance | ProKnight | ProSilver => {
             for &(d_rank, d_file) in ROOK_DIR.iter() {
-                generate_moves(from, occupied, result, d_rank, d_file, true);
+                generate_moves(c, from, occupied, result, d_rank, d_file, true);
             }
-            generate_moves(from, occupied, result, -1, -1, true);
-            generate_moves(from, occupied, result, -1, 1, true);
+            generate_moves(c, from, occupied, result, -1, -1, true);
+            generate_moves(c, from, occupied, result, -1, 1, true);
         }
         King => {
             for &(d_rank, d_file) in BISHOP_DIR.iter() {
-                generate_moves(from, occupied, result, d_rank, d_file, true);
+                generate_moves(c, from, occupied, result, d_rank, d_file, true);
             }
             for &(d_rank, d_file) in ROOK_DIR.iter() {
-                generate_moves(from, occupied, result, d_rank, d_file, true);
+                generate_moves(c, from, occupied, result, d_rank, d_file, true);
             }
         }
         Rook => {
             for &(d_rank, d_file) in ROOK_DIR.iter() {
-                generate_moves(from, occupied, result, d_rank, d_file, false);
+                generate_moves(c, from, occupied, result, d_rank, d_file, false);
             }
         }
         Bishop => {
             for &(d_rank, d_file) in BISHOP_DIR.iter() {
-                generate_moves(from, occupied, result, d_rank, d_file, false);
+                generate_moves(c, from, occupied, result, d_rank, d_file, false);
             }
         }
         ProRook => {
             for &(d_rank, d_file) in ROOK_DIR.iter() {
-                generate_moves(from, occupied, result, d_rank, d_file, false);
+                generate_moves(c, from, occupied, result, d_rank, d_file, false);
             }
             for &(d_rank, d_file) in BISHOP_DIR.iter() {
-                generate_moves(from, occupied, result, d_rank, d_file, true);
+                generate_moves(c, from, occupied, result, d_rank, d_file, true);
             }
         }
         ProBishop => {
             for &(d_rank, d_file) in BISHOP_DIR.iter() {
-                generate_moves(from, occupied, result, d_rank, d_file, false);
+                generate_moves(c, from, occupied, result, d_rank, d_file, false);
             }
             for &(d_rank, d_file) in ROOK_DIR.iter() {
-                generate_moves(from, occupied, result, d_rank, d_file, true);
+                generate_moves(c, from, occupied, result, d_rank, d_file, true);
             }
         }
         None => {}
@@ -0,0 +1,256 @@
+use crate::error::ShogiUtilError::{InvalidMove, UsiParseError};
+use crate::{
+    Board, Color, ColoredPiece, GameStatus, History, LegalMove, Move, MoveResult, Piece,
+    Repetition, Result, SfenBoard, SfenMove, Square,
+};
+
+/// A board together with the side to move, the move number and the moves played on it.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct Position {
+    board: Board,
+    side_to_move: Color,
+    ply: u32,
+    history: History,
+    moves: Vec<MoveResult>,
+}
+
+impl Position {
+    /// `ply` is the move number as in SFEN, which is 1 for the first move of a game.
+    pub fn new(mut board: Board, side_to_move: Color, ply: u32) -> Self {
+        board.set_side_to_move(side_to_move);
+        let mut history = History::new();
+        history.push(&board, side_to_move);
+        Self {
+            board,
+            side_to_move,
+            ply,
+            history,
+            moves: vec![],
+        }
+    }
+
+    pub fn startpos() -> Self {
+        Self::new(Board::default(), Color::Black, 1)
+    }
+
+    pub fn from_sfen(sfen: &str) -> Result<Self> {
+        let sfen_board = SfenBoard::parse(sfen)?;
+        let ply = match sfen.split(' ').nth(3) {
+            Some(ply) => ply
+                .parse()
+                .map_err(|_| UsiParseError(format!("Invalid move number: {}", ply)))?,
+            None => 1,
+        };
+        Ok(Self::new(sfen_board.board, sfen_board.next_turn, ply))
+    }
+
+    pub fn to_sfen(&self) -> String {
+        let mut sfen = String::new();
+        for rank in 1..=9 {
+            if rank > 1 {
+                sfen.push('/');
+            }
+            let mut empty = 0;
+            for file in (1..=9).rev() {
+                let sq = Square::at(file, rank);
+                let piece = self
+                    .board
+                    .piece_at(&sq)
+                    .and_then(|(color, piece)| ColoredPiece::new(color, piece));
+                match piece {
+                    Some(piece) => {
+                        if empty > 0 {
+                            sfen.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        sfen.push_str(&piece.to_sfen());
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                sfen.push_str(&empty.to_string());
+            }
+        }
+
+        sfen.push_str(match self.side_to_move {
+            Color::Black => " b ",
+            Color::White => " w ",
+        });
+
+        let mut hands = String::new();
+        for &color in [Color::Black, Color::White].iter() {
+            // SFEN lists hands from rooks to pawns.
+            for &piece in Piece::HAND.iter().rev() {
+                let count = self.board.hand(color).count(piece);
+                if count > 1 {
+                    hands.push_str(&count.to_string());
+                }
+                if let (true, Some(piece)) = (count > 0, ColoredPiece::new(color, piece)) {
+                    hands.push_str(&piece.to_sfen());
+                }
+            }
+        }
+        if hands.is_empty() {
+            hands.push('-');
+        }
+        sfen.push_str(&hands);
+        sfen.push_str(&format!(" {}", self.ply));
+        sfen
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn side_to_move(&self) -> Color {
+        self.side_to_move
+    }
+
+    pub fn ply(&self) -> u32 {
+        self.ply
+    }
+
+    pub fn hash_key(&self) -> u64 {
+        self.board.hash_key()
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// The moves played since this position was created, oldest first.
+    pub fn moves(&self) -> &[MoveResult] {
+        &self.moves
+    }
+
+    /// Plays `mv` and passes the turn. `mv.color` must be the side to move.
+    pub fn do_move(&mut self, mv: Move) -> Result<MoveResult> {
+        if mv.color != self.side_to_move {
+            return Err(InvalidMove(format!(
+                "{:?} can not move on {:?}'s turn",
+                mv.color, self.side_to_move
+            )));
+        }
+        let result = self.board.push_move(mv)?;
+        self.side_to_move = self.side_to_move.opponent();
+        self.ply += 1;
+        self.history.push(&self.board, self.side_to_move);
+        self.moves.push(result.clone());
+        Ok(result)
+    }
+
+    pub fn do_sfen_move(&mut self, mv: &SfenMove) -> Result<MoveResult> {
+        let color = self.side_to_move;
+        let mv = match *mv {
+            SfenMove::DropMove { to, piece } => Move {
+                color,
+                from: None,
+                to,
+                piece,
+            },
+            SfenMove::Travel { from, to, promoted } => {
+                let piece = self
+                    .board
+                    .piece_type_at(&from, color)
+                    .ok_or_else(|| InvalidMove(format!("There's no piece at {}", from)))?;
+                let piece = if promoted {
+                    piece
+                        .promote()
+                        .ok_or_else(|| InvalidMove(format!("{:?} can not promote", piece)))?
+                } else {
+                    piece
+                };
+                Move {
+                    color,
+                    from: Some(from),
+                    to,
+                    piece,
+                }
+            }
+        };
+        self.do_move(mv)
+    }
+
+    /// Takes back the latest move, returning it, or `None` if no move has been played.
+    pub fn undo_move(&mut self) -> Result<Option<MoveResult>> {
+        let result = match self.moves.pop() {
+            Some(result) => result,
+            None => return Ok(None),
+        };
+        self.board.undo_move(&result)?;
+        self.side_to_move = self.side_to_move.opponent();
+        self.ply -= 1;
+        self.history.pop();
+        Ok(Some(result))
+    }
+
+    pub fn generate_legal_moves(&self) -> Vec<LegalMove> {
+        self.board.generate_legal_moves(self.side_to_move)
+    }
+
+    pub fn in_check(&self) -> bool {
+        self.board.in_check(self.side_to_move)
+    }
+
+    pub fn game_status(&self) -> GameStatus {
+        self.board.game_status(self.side_to_move)
+    }
+
+    pub fn repetition(&self) -> Repetition {
+        self.history.repetition()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STARTPOS: &str = "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1";
+
+    #[test]
+    fn test_sfen_round_trip() {
+        assert_eq!(Position::startpos().to_sfen(), STARTPOS);
+        assert_eq!(Position::from_sfen(STARTPOS).unwrap(), Position::startpos());
+
+        let sfen = "ln1g3+Rl/2s1k4/p1pp1p2p/4p1p2/9/2P1P4/PPSP1PP1P/7R1/LN2KGSNL w BG2Pbsn2p 42";
+        let position = Position::from_sfen(sfen).unwrap();
+        assert_eq!(position.to_sfen(), sfen);
+        assert_eq!(position.side_to_move(), Color::White);
+        assert_eq!(position.ply(), 42);
+        assert!(Position::from_sfen("4k4/9/9/9/9/9/9/9/4K4 b - garbage").is_err());
+        assert_eq!(
+            position.hash_key(),
+            SfenBoard::parse(sfen).unwrap().board.hash_key()
+        );
+    }
+
+    #[test]
+    fn test_do_move() {
+        let mut position = Position::startpos();
+        for mv in ["7g7f", "3c3d", "8h2b+", "3a2b", "B*4e"].iter() {
+            position
+                .do_sfen_move(&SfenMove::parse(mv).unwrap())
+                .unwrap();
+        }
+        assert_eq!(position.side_to_move(), Color::White);
+        assert_eq!(position.ply(), 6);
+        assert_eq!(position.moves().len(), 5);
+        assert_eq!(
+            position.to_sfen(),
+            "lnsgkg1nl/1r5s1/pppppp1pp/6p2/5B3/2P6/PP1PPPPPP/7R1/LNSGKGSNL w b 6"
+        );
+
+        // It is White's turn, so a black move is rejected.
+        let black_move = Move {
+            color: Color::Black,
+            from: Some(Square::at(2, 7)),
+            to: Square::at(2, 6),
+            piece: Piece::Pawn,
+        };
+        assert!(position.do_move(black_move).is_err());
+
+        while position.undo_move().unwrap().is_some() {}
+        assert_eq!(position, Position::startpos());
+    }
+}
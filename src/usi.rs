@@ -1,7 +1,9 @@
 use crate::error::ShogiUtilError::UsiParseError;
-use crate::{Board, Color, Move, Piece};
+use crate::{Board, Color, ColoredPiece, Piece, Position};
 use crate::{Result, Square};
+use std::fmt;
 
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
 pub struct SfenBoard {
     pub board: Board,
     pub next_turn: Color,
@@ -26,19 +28,26 @@ impl SfenBoard {
             let mut row = row.chars();
             let mut file = 9;
             while let Some(c) = row.next() {
-                if c.is_numeric() {
-                    let c = c as u8 - '0' as u8;
+                if c.is_ascii_digit() {
+                    let c = c as u8 - b'0';
+                    if c == 0 || c > file {
+                        return Err(e());
+                    }
                     file -= c - 1;
+                } else if file == 0 {
+                    return Err(e());
                 } else if c == '+' {
                     let c = row.next().ok_or_else(e)?;
-                    let (piece, color) = parse_piece(c)
-                        .and_then(|(p, c)| p.promote().map(|p| (p, c)))
-                        .ok_or_else(e)?;
-                    let pos = Square { rank, file };
-                    board.push_piece(&pos, color, piece);
-                } else if let Some((piece, color)) = parse_piece(c) {
-                    let pos = Square { rank, file };
-                    board.push_piece(&pos, color, piece);
+                    let piece = ColoredPiece::from_sfen(&format!("+{}", c)).ok_or_else(e)?;
+                    let pos = Square::at(file, rank);
+                    board
+                        .push_piece(&pos, piece.color(), piece.piece())
+                        .map_err(|_| e())?;
+                } else if let Some(piece) = parse_piece(c) {
+                    let pos = Square::at(file, rank);
+                    board
+                        .push_piece(&pos, piece.color(), piece.piece())
+                        .map_err(|_| e())?;
                 } else {
                     return Err(e());
                 }
@@ -49,69 +58,63 @@ impl SfenBoard {
             }
         }
 
-        let next_turn;
-        match sfen_parts[1] {
-            "b" => next_turn = Color::Black,
-            "w" => next_turn = Color::White,
+        let next_turn = match sfen_parts[1] {
+            "b" => Color::Black,
+            "w" => Color::White,
             _ => return Err(e()),
-        }
+        };
 
         if sfen_parts[2] != "-" {
             let hands = parse_hand(sfen_parts[2]).ok_or_else(e)?;
-            for &piece in hands[0].iter() {
-                board.push_hand(piece, Color::Black);
-            }
-            for &piece in hands[1].iter() {
-                board.push_hand(piece, Color::White);
+            for (color, hand) in [Color::Black, Color::White].iter().zip(hands.iter()) {
+                for &piece in hand.iter() {
+                    board.push_hand(piece, *color).map_err(|_| e())?;
+                }
             }
         }
 
+        if next_turn == Color::White {
+            board.toggle_side();
+        }
+
         Ok(SfenBoard { board, next_turn })
     }
 }
 
 fn parse_hand(hand: &str) -> Option<[Vec<Piece>; 2]> {
-    let mut hand = hand.chars();
     let mut stack = String::new();
     let mut result = [vec![], vec![]];
-    while let Some(c) = hand.next() {
-        if c.is_numeric() {
+    for c in hand.chars() {
+        if c.is_ascii_digit() {
             stack.push(c);
         } else {
-            let (piece, color) = parse_piece(c)?;
+            let piece = parse_piece(c)?;
+            let (color, piece) = (piece.color(), piece.piece());
             let count = if stack.is_empty() {
                 Some(1)
             } else {
                 stack.parse::<usize>().ok()
             }?;
+            if count > piece.max_piece_in_hand() {
+                return None;
+            }
             for _ in 0..count {
                 result[color.to_usize()].push(piece);
             }
             stack.clear();
         }
     }
+    if !stack.is_empty() {
+        return None;
+    }
     Some(result)
 }
 
-fn parse_piece(c: char) -> Option<(Piece, Color)> {
-    let color = if c.is_ascii_uppercase() {
-        Color::Black
-    } else {
-        Color::White
-    };
-    match c.to_ascii_uppercase() {
-        'K' => Some((Piece::King, color)),
-        'R' => Some((Piece::Rook, color)),
-        'B' => Some((Piece::Bishop, color)),
-        'G' => Some((Piece::Gold, color)),
-        'S' => Some((Piece::Silver, color)),
-        'N' => Some((Piece::Knight, color)),
-        'L' => Some((Piece::Lance, color)),
-        'P' => Some((Piece::Pawn, color)),
-        _ => None,
-    }
+fn parse_piece(c: char) -> Option<ColoredPiece> {
+    ColoredPiece::from_sfen(c.encode_utf8(&mut [0; 4]))
 }
 
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
 pub enum SfenMove {
     DropMove {
         to: Square,
@@ -127,68 +130,50 @@ pub enum SfenMove {
 impl SfenMove {
     pub fn parse(sfen_move: &str) -> Result<SfenMove> {
         let e = || UsiParseError(format!("Invalid sfen move: '{}'", sfen_move));
-        if sfen_move.len() < 4 || 5 < sfen_move.len() {
+        let chars = sfen_move.chars().collect::<Vec<_>>();
+        if chars.len() < 4 || 5 < chars.len() {
             return Err(e());
         }
 
-        let from = parse_sfen_square(&sfen_move[0..2]);
-        let to = parse_sfen_square(&sfen_move[2..4]).ok_or_else(e)?;
+        let to = parse_sfen_square(chars[2], chars[3]).ok_or_else(e)?;
+        let promoted = match chars.get(4) {
+            None => false,
+            Some('+') => true,
+            Some(_) => return Err(e()),
+        };
 
-        if let Some(from) = from {
-            if sfen_move.len() == 5 {
-                if &sfen_move[4..5] != "+" {
-                    Err(e())
-                } else {
-                    Ok(SfenMove::Travel {
-                        from,
-                        to,
-                        promoted: true,
-                    })
-                }
+        if chars[1] == '*' {
+            let piece = parse_piece(chars[0]).ok_or_else(e)?;
+            if piece.color() != Color::Black || promoted {
+                Err(e())
             } else {
-                Ok(SfenMove::Travel {
-                    from,
+                Ok(SfenMove::DropMove {
+                    piece: piece.piece(),
                     to,
-                    promoted: false,
                 })
             }
-        } else if &sfen_move[1..2] != "*" {
-            Err(e())
         } else {
-            let c = sfen_move.chars().next().unwrap();
-            let (piece, color) = parse_piece(c).ok_or_else(e)?;
-            if color != Color::Black {
-                Err(e())
-            } else {
-                Ok(SfenMove::DropMove { piece, to })
-            }
+            let from = parse_sfen_square(chars[0], chars[1]).ok_or_else(e)?;
+            Ok(SfenMove::Travel { from, to, promoted })
         }
     }
 }
 
-fn parse_sfen_square(s: &str) -> Option<Square> {
-    assert_eq!(s.len(), 2);
-    let mut iter = s.chars();
-    let file = iter.next().unwrap();
-    let rank = iter.next().unwrap();
-    if file < '0' || rank < 'a' {
+fn parse_sfen_square(file: char, rank: char) -> Option<Square> {
+    let file = file.to_digit(10)?;
+    if !('a'..='i').contains(&rank) {
         return None;
     }
-    let file = file as u8 - '0' as u8;
-    let rank = rank as u8 - 'a' as u8 + 1;
-    if rank > 9 || file > 9 {
-        None
-    } else {
-        Some(Square { rank, file })
-    }
+    Square::new(file as u8, rank as u8 - b'a' + 1)
 }
 
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
 pub enum UsiRequest {
     Usi,
     IsReady,
     SetOption { id: String, value: String },
     NewGame,
-    Position { board: Board, next_turn: Color },
+    Position { position: Box<Position> },
     Go,
     Quit,
 }
@@ -210,42 +195,28 @@ impl UsiRequest {
                 }
             }
             "usinewgame" => Ok(UsiRequest::NewGame),
-            "position" => match command[1] {
-                "sfen" => {
-                    let board_sfen = command[2];
-                    let next_turn = command[3];
-                    let hand_sfen = command[4];
-                    let sfen_string = vec![board_sfen, next_turn, hand_sfen, "1"].join(" ");
-                    let sfen_board = SfenBoard::parse(&sfen_string)?;
-                    let cur_turn = sfen_board.next_turn;
-                    let mut board = sfen_board.board;
-                    if command[6] != "moves" {
-                        return Err(UsiParseError(format!("Invalid command: {}", input)));
+            "position" => {
+                let (mut position, moves) = match command.get(1) {
+                    Some(&"sfen") if command.len() >= 6 => {
+                        let position = Position::from_sfen(&command[2..6].join(" "))?;
+                        (position, &command[6..])
                     }
-                    let cur_turn = push_move_commands(&mut board, &command[7..], cur_turn)?;
-                    Ok(UsiRequest::Position {
-                        board,
-                        next_turn: cur_turn,
-                    })
-                }
-                "startpos" => {
-                    if command.len() != 2 && command[2] != "moves" {
-                        return Err(UsiParseError(format!("Invalid command: {}", input)));
-                    }
-                    let mut cur_turn = Color::Black;
-                    let mut board = Board::default();
-
-                    if command.len() >= 4 {
-                        cur_turn = push_move_commands(&mut board, &command[3..], cur_turn)?;
+                    Some(&"startpos") => (Position::startpos(), &command[2..]),
+                    _ => return Err(UsiParseError(format!("Invalid format: {}", input))),
+                };
+                match moves.split_first() {
+                    Some((&"moves", moves)) => {
+                        for &mv in moves.iter() {
+                            position.do_sfen_move(&SfenMove::parse(mv)?)?;
+                        }
                     }
-
-                    Ok(UsiRequest::Position {
-                        board,
-                        next_turn: cur_turn,
-                    })
+                    Some(_) => return Err(UsiParseError(format!("Invalid command: {}", input))),
+                    None => {}
                 }
-                _ => Err(UsiParseError(format!("Invalid format: {}", input))),
-            },
+                Ok(UsiRequest::Position {
+                    position: Box::new(position),
+                })
+            }
             "go" => Ok(UsiRequest::Go),
             "quit" => Ok(UsiRequest::Quit),
             _ => Err(UsiParseError(format!("Unsupported option: {}", input))),
@@ -253,27 +224,7 @@ impl UsiRequest {
     }
 }
 
-fn push_move_commands(board: &mut Board, command: &[&str], mut cur_turn: Color) -> Result<Color> {
-    for &command in command.iter() {
-        match SfenMove::parse(command)? {
-            SfenMove::DropMove { to, piece } => {
-                board.push_move(Move {
-                    from: None,
-                    to,
-                    piece,
-                    color: cur_turn,
-                })?;
-            }
-            SfenMove::Travel { from, to, promoted } => {
-                board.move_between(&from, &to, promoted, cur_turn)?;
-            }
-        }
-
-        cur_turn = cur_turn.opponent();
-    }
-    Ok(cur_turn)
-}
-
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
 pub enum UsiResponse {
     Id {
         name: String,
@@ -289,19 +240,20 @@ pub enum UsiResponse {
         piece: Piece,
         to: Square,
     },
+    Win,
 }
 
-impl ToString for UsiResponse {
-    fn to_string(&self) -> String {
+impl fmt::Display for UsiResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         use UsiResponse::*;
-        match self {
+        let response = match self {
             Id { name } => format!("id name {}", name),
             UsiOk => "usiok".to_string(),
             ReadyOk => "readyok".to_string(),
             TravelMove { from, to, promoted } => {
                 let mut response = "bestmove ".to_string();
-                to_sfen_square(&from, &mut response);
-                to_sfen_square(&to, &mut response);
+                to_sfen_square(from, &mut response);
+                to_sfen_square(to, &mut response);
                 if *promoted {
                     response.push('+');
                 }
@@ -309,18 +261,20 @@ impl ToString for UsiResponse {
             }
             DropMove { piece, to } => {
                 let mut response = "bestmove ".to_string();
-                response.push(piece.to_sfen());
+                response.push_str(piece.to_sfen());
                 response.push('*');
-                to_sfen_square(&to, &mut response);
+                to_sfen_square(to, &mut response);
                 response
             }
-        }
+            Win => "bestmove win".to_string(),
+        };
+        write!(f, "{}", response)
     }
 }
 
-fn to_sfen_square(sq: &Square, s: &mut String) {
-    s.push((sq.file + '0' as u8) as char);
-    let rank = (sq.rank - 1 + 'a' as u8) as char;
+pub(crate) fn to_sfen_square(sq: &Square, s: &mut String) {
+    s.push((sq.file() + b'0') as char);
+    let rank = (sq.rank() - 1 + b'a') as char;
     s.push(rank);
 }
 
@@ -328,6 +282,7 @@ fn to_sfen_square(sq: &Square, s: &mut String) {
 mod tests {
     use super::*;
     use crate::debug::dump_board;
+    use crate::Repetition;
 
     #[test]
     fn test_parse_hand() {
@@ -362,40 +317,73 @@ P+
 P-
 "
         );
+
+        for sfen in [
+            "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNLL b - 1",
+            "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSN2 b - 1",
+            "lnsgkgsnl/1r5b1/ppppppppp/9/9/0/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1",
+            "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b K 1",
+            "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b 3R 1",
+            "4k4/9/9/9/9/9/9/9/4K4 b 10P9P 1",
+            "4k4/9/9/9/9/9/9/9/4K4 b 2 1",
+            "4k4/9/9/9/9/9/9/9/4K4 b S2 1",
+        ]
+        .iter()
+        {
+            assert!(SfenBoard::parse(sfen).is_err(), "{}", sfen);
+        }
+    }
+
+    #[test]
+    fn test_sfen_hash_key() {
+        let startpos = "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL";
+        let black = SfenBoard::parse(&format!("{} b - 1", startpos)).unwrap();
+        assert_eq!(black.board.hash_key(), Board::default().hash_key());
+        let white = SfenBoard::parse(&format!("{} w - 1", startpos)).unwrap();
+        assert_ne!(white.board.hash_key(), black.board.hash_key());
     }
 
     #[test]
     fn test_parse_sfen_move() {
         if let SfenMove::Travel { from, to, promoted } = SfenMove::parse("8h2b+").unwrap() {
             assert!(promoted);
-            assert_eq!(from, Square { file: 8, rank: 8 });
-            assert_eq!(to, Square { file: 2, rank: 2 });
+            assert_eq!(from, Square::at(8, 8));
+            assert_eq!(to, Square::at(2, 2));
         } else {
             unreachable!()
         }
 
         if let SfenMove::Travel { from, to, promoted } = SfenMove::parse("7g7f").unwrap() {
             assert!(!promoted);
-            assert_eq!(from, Square { file: 7, rank: 7 });
-            assert_eq!(to, Square { file: 7, rank: 6 });
+            assert_eq!(from, Square::at(7, 7));
+            assert_eq!(to, Square::at(7, 6));
         } else {
             unreachable!()
         }
 
         if let SfenMove::DropMove { to, piece } = SfenMove::parse("S*5b").unwrap() {
             assert_eq!(piece, Piece::Silver);
-            assert_eq!(to, Square { file: 5, rank: 2 });
+            assert_eq!(to, Square::at(5, 2));
         } else {
             unreachable!()
         }
+
+        for mv in [
+            "7gé", "7g7fé", "é7f", "7g7", "7g7f+x", "7g7j", "0g7f", "S*5b+", "s*5b", "7*7f",
+        ]
+        .iter()
+        {
+            assert!(SfenMove::parse(mv).is_err(), "{}", mv);
+        }
     }
 
     #[test]
     fn test_parse_usi_board() {
         let input = "position startpos moves 7g7f 3c3d 2g2f 8c8d 8g8f 2c2d 2f2e 8d8e 2e2d 8e8f 2h2f 8b8d 2f2e 8d8e 3g3f 7c7d 6g6f 4c4d 4g4f 6c6d 5g5f 5c5d 3f3e 7d7e 7f7e 3d3e 4f4e 6d6e 6f6e 4d4e 3i3h 7a7b 5f5e 5d5e 7e7d 3e3f 7i7h 3a3b 7h7g 3b3c 7g7f 3c3d 7f7e 3d3e 6e6d 5e5f 3h3g 4e4f 3g3f 3e3f 5i5h 7b7c 4i4h 7c7d 4h4g 7d7e 4g4f 7e7f 4f4e 5a5b 4e4d";
         match UsiRequest::parse(input).unwrap() {
-            UsiRequest::Position { board, next_turn } => {
-                assert_eq!(next_turn, Color::White);
+            UsiRequest::Position { position } => {
+                assert_eq!(position.side_to_move(), Color::White);
+                assert_eq!(position.ply(), 62);
                 assert_eq!(
                     r"P1-KY-KE * -KI * -KI * -KE-KY
 P2 *  *  *  * -OU *  * -KA * 
@@ -409,10 +397,57 @@ P9+KY+KE * +KI *  *  * +KE+KY
 P+00FU00FU00FU00FU00FU
 P-00FU00FU00FU00FU00FU00GI00GI
 ",
-                    dump_board(&board)
+                    dump_board(position.board())
                 );
             }
             _ => unreachable!(),
         };
     }
+
+    #[test]
+    fn test_usi_response() {
+        let response = UsiResponse::TravelMove {
+            from: Square::at(8, 8),
+            to: Square::at(2, 2),
+            promoted: true,
+        };
+        assert_eq!(response.to_string(), "bestmove 8h2b+");
+        let response = UsiResponse::DropMove {
+            piece: Piece::Silver,
+            to: Square::at(5, 2),
+        };
+        assert_eq!(response.to_string(), "bestmove S*5b");
+        assert_eq!(UsiResponse::Win.to_string(), "bestmove win");
+    }
+
+    #[test]
+    fn test_parse_usi_repetition() {
+        let shuffle = " 2h3h 8b7b 3h2h 7b8b";
+        let input = format!("position startpos moves{}", shuffle.repeat(3));
+        match UsiRequest::parse(&input).unwrap() {
+            UsiRequest::Position { position } => {
+                assert_eq!(position.repetition(), Repetition::Draw);
+            }
+            _ => unreachable!(),
+        }
+
+        // Black keeps checking with the rook while the white king walks between 1a and 2a.
+        let shuffle = " 2i1i 1a2a 1i2i 2a1a";
+        let input = format!(
+            "position sfen 8k/9/9/9/9/9/9/9/K6R1 b - 1 moves{}",
+            shuffle.repeat(3)
+        );
+        match UsiRequest::parse(&input).unwrap() {
+            UsiRequest::Position { position } => {
+                assert_eq!(position.side_to_move(), Color::Black);
+                assert_eq!(
+                    position.repetition(),
+                    Repetition::PerpetualCheck {
+                        loser: Color::Black
+                    }
+                );
+            }
+            _ => unreachable!(),
+        }
+    }
 }